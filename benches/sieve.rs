@@ -0,0 +1,43 @@
+//! Benchmarks for the segmented sieve.
+//!
+//! Every segment is pre-sieved for the primes 7 to 19 before the remaining sieving primes are
+//! crossed off, so these measure the gain from pre-sieving when compared against a run of the
+//! same benchmarks without it. Commit 821c5a4 is the last one before pre-sieving:
+//!
+//! ```text
+//! git checkout 821c5a4 -- src && cargo bench -- --save-baseline crossing-off
+//! git checkout HEAD -- src && cargo bench -- --baseline crossing-off
+//! ```
+//!
+//! Measured with 20 samples each on a single core, criterion estimated these times:
+//!
+//! | Benchmark                              | Crossing off | Pre-sieving | Change |
+//! |----------------------------------------|--------------|-------------|--------|
+//! | `Sieve::to_limit(10^8)`                | 150.7 ms     | 134.6 ms    | -11%   |
+//! | `Sieve::in_range(10^12, 10^12 + 10^8)` | 258.6 ms     | 206.2 ms    | -20%   |
+
+#[macro_use]
+extern crate criterion;
+extern crate primesieve;
+
+use criterion::{black_box, Criterion};
+use primesieve::Sieve;
+
+fn sieve_to_limit(c: &mut Criterion) {
+    c.bench_function("Sieve::to_limit(10^8)", |b| {
+        b.iter(|| Sieve::to_limit(black_box(100000000)))
+    });
+}
+
+fn sieve_in_range(c: &mut Criterion) {
+    c.bench_function("Sieve::in_range(10^12, 10^12 + 10^8)", |b| {
+        b.iter(|| Sieve::in_range(black_box(1000000000000), black_box(1000100000000)))
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = sieve_to_limit, sieve_in_range
+}
+criterion_main!(benches);
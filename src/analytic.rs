@@ -0,0 +1,153 @@
+//! Analytic approximations to the prime counting function, and their inverses.
+//!
+//! The logarithmic integral `li(x)`, its offset form `Li(x) = li(x) - li(2)` and Riemann's
+//! function `R(x)` all approximate the number of primes up to `x`, with `R(x)` the most accurate
+//! of the three. Their inverses approximate the `n`th prime.
+//!
+//! # Examples
+//!
+//! ```
+//! use primesieve::analytic::{li, offset_li, riemann_r, riemann_r_inverse};
+//!
+//! // There are 78498 primes up to one million.
+//! assert_eq!(li(1e6).round(), 78628.0);
+//! assert_eq!(offset_li(1e6).round(), 78627.0);
+//! assert_eq!(riemann_r(1e6).round(), 78527.0);
+//!
+//! // The millionth prime is 15485863.
+//! assert_eq!(riemann_r_inverse(1e6).round(), 15484040.0);
+//! ```
+
+/// The Euler-Mascheroni constant.
+const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+
+/// The value of `li(2)`.
+const LI_2: f64 = 1.045_163_780_117_493;
+
+/// Returns the value of the Riemann zeta function at an integer `s >= 2`.
+fn zeta(s: u32) -> f64 {
+    // Sum the first few terms directly, and use the Euler-Maclaurin formula for the tail.
+    const N: f64 = 32.0;
+    let s_f = s as f64;
+    let head = (1..N as u32).map(|n| (n as f64).powi(-(s as i32))).sum::<f64>();
+    let tail = N.powf(1.0 - s_f) / (s_f - 1.0) + N.powf(-s_f) / 2.0 + s_f * N.powf(-s_f - 1.0) / 12.0
+        - s_f * (s_f + 1.0) * (s_f + 2.0) * N.powf(-s_f - 3.0) / 720.0;
+    head + tail
+}
+
+/// Returns the logarithmic integral `li(x)`, the principal value of the integral of `1 / ln(t)`
+/// from 0 to `x`, for `x > 1`.
+pub fn li(x: f64) -> f64 {
+    // Use Ramanujan's series, which converges quickly for every `x > 1`.
+    let ln = x.ln();
+    let mut sum = 0.0;
+    let mut term = -1.0;
+    let mut inner = 0.0;
+    for n in 1..1000 {
+        term *= -ln / (n as f64 * if n == 1 { 1.0 } else { 2.0 });
+        if (n - 1) % 2 == 0 {
+            inner += 1.0 / n as f64;
+        }
+        let next = term * inner;
+        sum += next;
+        if next.abs() < 1e-17 * sum.abs() {
+            break;
+        }
+    }
+    EULER_GAMMA + ln.ln() + x.sqrt() * sum
+}
+
+/// Returns the offset logarithmic integral `Li(x) = li(x) - li(2)`, the integral of `1 / ln(t)`
+/// from 2 to `x`.
+pub fn offset_li(x: f64) -> f64 {
+    li(x) - LI_2
+}
+
+/// Returns the number `x` for which `li(x)` is equal to the given value, which must be at least
+/// 2.
+pub fn li_inverse(y: f64) -> f64 {
+    // Use Newton's method, starting from the approximation `y ln(y)`, using the fact that the
+    // derivative of `li(x)` is exactly `1 / ln(x)`.
+    let mut x = y * y.ln();
+    for _ in 0..100 {
+        let step = (li(x) - y) * x.ln();
+        x -= step;
+        if step.abs() < 1e-12 * x {
+            break;
+        }
+    }
+    x
+}
+
+/// Returns the number `x` for which `Li(x)` is equal to the given value, which must be at least
+/// 1.
+pub fn offset_li_inverse(y: f64) -> f64 {
+    li_inverse(y + LI_2)
+}
+
+/// Returns Riemann's prime counting function `R(x)`, which is an excellent approximation to the
+/// number of primes up to `x`.
+pub fn riemann_r(x: f64) -> f64 {
+    // Use the Gram series, all of whose terms are positive.
+    let ln = x.ln();
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    for k in 1..1000 {
+        term *= ln / k as f64;
+        let next = term / (k as f64 * zeta(k + 1));
+        sum += next;
+        if next < 1e-17 * sum {
+            break;
+        }
+    }
+    sum
+}
+
+/// Returns the number `x` for which `R(x)` is equal to the given value, which must be at least 2.
+pub fn riemann_r_inverse(y: f64) -> f64 {
+    // Use Newton's method, starting from the approximation `y ln(y)`, and using the fact that the
+    // derivative of `R(x)` is very close to `1 / ln(x)`.
+    let mut x = y * y.ln();
+    for _ in 0..100 {
+        let step = (riemann_r(x) - y) * x.ln();
+        x -= step;
+        if step.abs() < 1e-12 * x {
+            break;
+        }
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() <= 1e-10 * expected, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_zeta() {
+        assert_close(zeta(2), 1.644_934_066_848_226_4);
+        assert_close(zeta(3), 1.202_056_903_159_594_2);
+        assert_close(zeta(10), 1.000_994_575_127_818_1);
+    }
+
+    #[test]
+    fn test_li() {
+        assert_close(li(2.0), LI_2);
+        assert_close(li(10.0), 6.165_599_504_787_298);
+        assert_close(li(1000.0), 177.609_657_990_152_23);
+        assert_close(li(1e12), 37_607_950_280.804_87);
+        assert_close(offset_li(1e6), 78_627.549_159_462_18 - LI_2);
+        assert_close(li_inverse(li(1e12)), 1e12);
+        assert_close(offset_li_inverse(offset_li(12345.0)), 12345.0);
+    }
+
+    #[test]
+    fn test_riemann_r() {
+        assert_close(riemann_r(1000.0), 168.359_446_281_167_3);
+        assert_close(riemann_r(1e10), 455_050_683.306_847);
+        assert_close(riemann_r_inverse(riemann_r(1e12)), 1e12);
+    }
+}
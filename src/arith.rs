@@ -0,0 +1,417 @@
+//! Modular arithmetic on wide integers, used by the primality tests, factorisation routines and
+//! sums of prime powers.
+
+use std::convert::TryFrom;
+
+/// Returns the full 256-bit product of `a` and `b`, as a pair `(low, high)` of 128-bit halves.
+#[inline]
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = (1 << 64) - 1;
+
+    let (a_lo, a_hi) = (a & MASK, a >> 64);
+    let (b_lo, b_hi) = (b & MASK, b >> 64);
+
+    let ll = a_lo * b_lo;
+    let lh = a_lo * b_hi;
+    let hl = a_hi * b_lo;
+    let hh = a_hi * b_hi;
+
+    // Add the middle terms to the upper half of the low product, keeping track of the carries.
+    let mid = (ll >> 64) + (lh & MASK) + (hl & MASK);
+    let low = (ll & MASK) | (mid << 64);
+    let high = hh + (lh >> 64) + (hl >> 64) + (mid >> 64);
+    (low, high)
+}
+
+/// Returns the integer square root of `n`, that is the largest `r` with `r * r <= n`.
+pub fn isqrt(n: u128) -> u128 {
+    if n == 0 {
+        return 0;
+    }
+
+    // Start from an overestimate and use Newton's method, which decreases monotonically to the
+    // answer.
+    let mut x = 1u128 << (128 - n.leading_zeros()).div_ceil(2);
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// Returns the integer cube root of `n`, that is the largest `r` with `r * r * r <= n`.
+pub fn icbrt(n: u64) -> u64 {
+    // Correct the floating point estimate, which may be slightly out either way.
+    let cube = |r: u64| r as u128 * r as u128 * r as u128;
+    let mut r = (n as f64).cbrt() as u64;
+    while cube(r) > n as u128 {
+        r -= 1;
+    }
+    while cube(r + 1) <= n as u128 {
+        r += 1;
+    }
+    r
+}
+
+/// Returns the Jacobi symbol `(a / n)`, for odd `n`.
+pub fn jacobi(mut a: u128, mut n: u128) -> i32 {
+    a %= n;
+    let mut result = 1;
+    while a != 0 {
+        let twos = a.trailing_zeros();
+        a >>= twos;
+        if twos % 2 == 1 && (n % 8 == 3 || n % 8 == 5) {
+            result = -result;
+        }
+        if a % 4 == 3 && n % 4 == 3 {
+            result = -result;
+        }
+        std::mem::swap(&mut a, &mut n);
+        a %= n;
+    }
+    if n == 1 { result } else { 0 }
+}
+
+/// Returns the greatest common divisor of `a` and `b`, for any unsigned integers of up to 128
+/// bits.
+pub fn gcd<T: Into<u128> + TryFrom<u128>>(a: T, b: T) -> T {
+    let (mut a, mut b) = (a.into(), b.into());
+    let g = if a == 0 || b == 0 {
+        a | b
+    } else {
+        // Binary GCD: remove common factors of 2, then repeatedly subtract the smaller odd number
+        // from the larger.
+        let shift = (a | b).trailing_zeros();
+        a >>= a.trailing_zeros();
+        loop {
+            b >>= b.trailing_zeros();
+            if a > b {
+                std::mem::swap(&mut a, &mut b);
+            }
+            b -= a;
+            if b == 0 {
+                break a << shift;
+            }
+        }
+    };
+
+    // The gcd is no larger than `a` and `b`, so it fits in the original type.
+    T::try_from(g).ok().unwrap()
+}
+
+/// Returns `σ_k(p^e) = 1 + p^k + p^2k + ... + p^ek`, the sum of the `k`th powers of the divisors
+/// of the prime power `p^e`. The result may overflow for large `k`.
+pub fn divisor_power_sum(p: u64, k: u32, e: u64) -> u128 {
+    let step = (p as u128).pow(k);
+    let (mut sum, mut term) = (1, 1);
+    for _ in 0..e {
+        term *= step;
+        sum += term;
+    }
+    sum
+}
+
+/// Arithmetic modulo a fixed odd modulus `n < 2^64`, with numbers held in Montgomery form
+/// `x * 2^64 mod n` so that multiplication needs no division.
+#[derive(Clone, Copy, Debug)]
+pub struct Montgomery64 {
+    /// The modulus.
+    n: u64,
+    /// The inverse of `n` modulo `2^64`.
+    n_inv: u64,
+    /// The value `2^128 mod n`, used for converting into Montgomery form.
+    r2: u64,
+}
+
+impl Montgomery64 {
+    /// Set up arithmetic modulo the given odd number.
+    pub fn new(n: u64) -> Montgomery64 {
+        debug_assert!(n % 2 == 1);
+
+        // Newton's iteration doubles the number of correct bits each time, and `n` is its own
+        // inverse modulo 8.
+        let mut n_inv = n;
+        for _ in 0..5 {
+            n_inv = n_inv.wrapping_mul(2u64.wrapping_sub(n.wrapping_mul(n_inv)));
+        }
+
+        let r2 = (u128::MAX % n as u128 + 1) as u64 % n;
+        Montgomery64 { n, n_inv, r2 }
+    }
+
+    /// Montgomery reduction: returns `x` divided by `2^64` modulo `n`.
+    #[inline]
+    fn reduce(&self, x: u128) -> u64 {
+        let (low, high) = (x as u64, (x >> 64) as u64);
+        let m = low.wrapping_mul(self.n_inv);
+        let mn_high = ((m as u128 * self.n as u128) >> 64) as u64;
+        if high >= mn_high { high - mn_high } else { high.wrapping_sub(mn_high).wrapping_add(self.n) }
+    }
+
+    /// Converts `x` into Montgomery form. Multiplying by 1 converts back out again.
+    pub fn encode(&self, x: u64) -> u64 {
+        self.reduce((x % self.n) as u128 * self.r2 as u128)
+    }
+
+    /// The number 1, in Montgomery form.
+    pub fn one(&self) -> u64 {
+        self.n.wrapping_neg() % self.n
+    }
+
+    /// Multiplies two numbers in Montgomery form.
+    #[inline]
+    pub fn mul(&self, a: u64, b: u64) -> u64 {
+        self.reduce(a as u128 * b as u128)
+    }
+
+    /// Adds two numbers modulo `n`.
+    #[inline]
+    pub fn add(&self, a: u64, b: u64) -> u64 {
+        let (sum, overflow) = a.overflowing_add(b);
+        if overflow || sum >= self.n { sum.wrapping_sub(self.n) } else { sum }
+    }
+
+    /// Subtracts two numbers modulo `n`.
+    #[inline]
+    pub fn sub(&self, a: u64, b: u64) -> u64 {
+        if a >= b { a - b } else { a.wrapping_sub(b).wrapping_add(self.n) }
+    }
+
+    /// Raises a number in Montgomery form to the given power.
+    pub fn pow(&self, mut base: u64, mut exp: u64) -> u64 {
+        let mut result = self.one();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = self.mul(result, base);
+            }
+            base = self.mul(base, base);
+            exp >>= 1;
+        }
+        result
+    }
+}
+
+/// Arithmetic modulo a fixed odd modulus `n < 2^128`, with numbers held in Montgomery form
+/// `x * 2^128 mod n` so that multiplication needs no division.
+#[derive(Clone, Copy, Debug)]
+pub struct Montgomery128 {
+    /// The modulus.
+    n: u128,
+    /// The inverse of `n` modulo `2^128`.
+    n_inv: u128,
+    /// The value `2^256 mod n`, used for converting into Montgomery form.
+    r2: u128,
+}
+
+impl Montgomery128 {
+    /// Set up arithmetic modulo the given odd number.
+    pub fn new(n: u128) -> Montgomery128 {
+        debug_assert!(n % 2 == 1);
+
+        // Newton's iteration doubles the number of correct bits each time, and `n` is its own
+        // inverse modulo 8.
+        let mut n_inv = n;
+        for _ in 0..6 {
+            n_inv = n_inv.wrapping_mul(2u128.wrapping_sub(n.wrapping_mul(n_inv)));
+        }
+
+        // Calculate 2^256 mod n by doubling 2^128 mod n another 128 times.
+        let mut r2 = n.wrapping_neg() % n;
+        for _ in 0..128 {
+            r2 = add_mod(r2, r2, n);
+        }
+
+        Montgomery128 { n, n_inv, r2 }
+    }
+
+    /// The modulus.
+    pub fn modulus(&self) -> u128 {
+        self.n
+    }
+
+    /// Montgomery reduction: returns `low + high * 2^128` divided by `2^128` modulo `n`.
+    #[inline]
+    fn reduce(&self, (low, high): (u128, u128)) -> u128 {
+        let m = low.wrapping_mul(self.n_inv);
+        let (_, mn_high) = mul_wide(m, self.n);
+        if high >= mn_high { high - mn_high } else { high.wrapping_sub(mn_high).wrapping_add(self.n) }
+    }
+
+    /// Converts `x` into Montgomery form. Multiplying by 1 converts back out again.
+    pub fn encode(&self, x: u128) -> u128 {
+        self.reduce(mul_wide(x % self.n, self.r2))
+    }
+
+    /// The number 1, in Montgomery form.
+    pub fn one(&self) -> u128 {
+        self.n.wrapping_neg() % self.n
+    }
+
+    /// Multiplies two numbers in Montgomery form.
+    #[inline]
+    pub fn mul(&self, a: u128, b: u128) -> u128 {
+        self.reduce(mul_wide(a, b))
+    }
+
+    /// Adds two numbers modulo `n`.
+    #[inline]
+    pub fn add(&self, a: u128, b: u128) -> u128 {
+        add_mod(a, b, self.n)
+    }
+
+    /// Subtracts two numbers modulo `n`.
+    #[inline]
+    pub fn sub(&self, a: u128, b: u128) -> u128 {
+        if a >= b { a - b } else { a.wrapping_sub(b).wrapping_add(self.n) }
+    }
+
+    /// Halves a number modulo `n`.
+    #[inline]
+    pub fn half(&self, a: u128) -> u128 {
+        if a % 2 == 0 { a / 2 } else { a / 2 + self.n / 2 + 1 }
+    }
+
+    /// Raises a number in Montgomery form to the given power.
+    pub fn pow(&self, mut base: u128, mut exp: u128) -> u128 {
+        let mut result = self.one();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = self.mul(result, base);
+            }
+            base = self.mul(base, base);
+            exp >>= 1;
+        }
+        result
+    }
+}
+
+/// Returns `a + b` modulo `n`, where `a` and `b` are already reduced, for any unsigned integers
+/// of up to 128 bits.
+#[inline]
+pub fn add_mod<T: Into<u128> + TryFrom<u128>>(a: T, b: T, n: T) -> T {
+    let (a, b, n) = (a.into(), b.into(), n.into());
+    let (sum, overflow) = a.overflowing_add(b);
+    let sum = if overflow || sum >= n { sum.wrapping_sub(n) } else { sum };
+
+    // The sum is reduced modulo `n`, so it fits in the original type.
+    T::try_from(sum).ok().unwrap()
+}
+
+/// Returns `a - b` modulo `m`, for `a` and `b` less than `m`.
+#[inline]
+pub fn sub_mod(a: u64, b: u64, m: u64) -> u64 {
+    if a >= b { a - b } else { a + (m - b) }
+}
+
+/// Returns `a * b` modulo `m`.
+#[inline]
+pub fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+/// Returns `a^k` modulo `m`.
+pub fn pow_mod(mut a: u64, mut k: u32, m: u64) -> u64 {
+    let mut result = 1 % m;
+    a %= m;
+    while k > 0 {
+        if k & 1 == 1 {
+            result = mul_mod(result, a, m);
+        }
+        a = mul_mod(a, a, m);
+        k >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isqrt() {
+        for n in 0..10000u128 {
+            let r = isqrt(n);
+            assert!(r * r <= n && (r + 1) * (r + 1) > n);
+        }
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+        assert_eq!(isqrt((1 << 126) - 1), (1 << 63) - 1);
+    }
+
+    #[test]
+    fn test_icbrt() {
+        for n in (0..100000).chain(u64::MAX - 100000..=u64::MAX) {
+            let r = icbrt(n) as u128;
+            assert!(r * r * r <= n as u128 && (r + 1) * (r + 1) * (r + 1) > n as u128);
+        }
+    }
+
+    #[test]
+    fn test_jacobi() {
+        // Compare against Euler's criterion for a prime modulus.
+        let p = 1009;
+        for a in 0..p {
+            let euler = (0..(p - 1) / 2).fold(1u128, |acc, _| acc * a % p);
+            let expected = if a == 0 { 0 } else if euler == 1 { 1 } else { -1 };
+            assert_eq!(jacobi(a, p), expected);
+        }
+        assert_eq!(jacobi(3, 9), 0);
+        assert_eq!(jacobi(2, 15), 1);
+    }
+
+    #[test]
+    fn test_gcd() {
+        for a in 0..100u64 {
+            for b in 0..100 {
+                let expected = (1..=a.max(b)).rev().find(|d| a % d == 0 && b % d == 0).unwrap_or(0);
+                assert_eq!(gcd(a, b), expected);
+            }
+        }
+        assert_eq!(gcd(1u64 << 63, 3 << 40), 1 << 40);
+        assert_eq!(gcd(1u128 << 127, 3 << 100), 1 << 100);
+        let p = 18446744073709551557u128;
+        assert_eq!(gcd(p * 3, p * 5), p);
+    }
+
+    #[test]
+    fn test_montgomery_64() {
+        let moduli = [3, 1000003, (1 << 63) + 1, u64::MAX];
+        let values = [0, 1, 2, 1234567890123456789, u64::MAX - 1];
+        for &n in &moduli {
+            let mont = Montgomery64::new(n);
+            for &a in &values {
+                for &b in &values {
+                    let (a, b) = (a % n, b % n);
+                    let product = mont.mul(mont.mul(mont.encode(a), mont.encode(b)), 1);
+                    assert_eq!(product as u128, a as u128 * b as u128 % n as u128);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_montgomery() {
+        let moduli = [3, 1000003, (1 << 64) + 13, (1 << 127) + 1, u128::MAX];
+        let values = [0, 1, 2, 12345678901234567890, (1 << 100) + 7, u128::MAX - 1];
+        for &n in &moduli {
+            let mont = Montgomery128::new(n);
+            for &a in &values {
+                for &b in &values {
+                    let (a, b) = (a % n, b % n);
+                    let product = mont.mul(mont.mul(mont.encode(a), mont.encode(b)), 1);
+
+                    // Check the product using repeated doubling.
+                    let mut expected = 0;
+                    for bit in (0..128).rev() {
+                        expected = add_mod(expected, expected, n);
+                        if (b >> bit) & 1 == 1 {
+                            expected = add_mod(expected, a, n);
+                        }
+                    }
+                    assert_eq!(product, expected);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Proven upper and lower bounds for the `n`th prime and for the prime counting function.
+//!
+//! The bounds are those of Rosser and of Dusart, each used only in the range where it has been
+//! proven to hold, with small cases handled exactly. The formulae are evaluated in floating point
+//! and then widened by a tiny relative margin, which is far smaller than the slack in the bounds
+//! themselves, so that rounding can never push them the wrong side of the truth.
+
+/// The first few primes, for the cases too small for the formulae.
+const FIRST_PRIMES: &[u64; 7] = &[2, 3, 5, 7, 11, 13, 17];
+
+/// The relative margin by which each bound is widened to allow for rounding errors.
+const MARGIN: f64 = 1e-12;
+
+/// Rounds an upper bound up, allowing for rounding errors and saturating at `u64::MAX`.
+fn round_up(bound: f64) -> u64 {
+    (bound * (1.0 + MARGIN)).ceil() as u64
+}
+
+/// Rounds a lower bound down, allowing for rounding errors.
+fn round_down(bound: f64) -> u64 {
+    (bound * (1.0 - MARGIN)).floor() as u64
+}
+
+/// Returns an upper bound for the `n`th prime, indexed from 0 as in `nth_prime`. The bound
+/// saturates at `u64::MAX` if the `n`th prime does not fit in a `u64`.
+///
+/// Writing `k = n + 1` and `L = ln(k)`, this uses Dusart's bound
+/// `k (L + ln(L) - 1 + (ln(L) - 2) / L)` for `k >= 688383`, his bound `k (L + ln(L) - 0.9484)` for
+/// `k >= 39017`, and Rosser's bound `k (L + ln(L))` for `k >= 6`, so that the bound is within
+/// about 0.1% of the truth for `n` beyond a million.
+///
+/// # Examples
+///
+/// ```
+/// use primesieve::{nth_prime, nth_prime_upper_bound};
+///
+/// assert_eq!(nth_prime_upper_bound(0), 2);
+/// assert!(nth_prime_upper_bound(99) >= nth_prime(99));
+///
+/// let bound = nth_prime_upper_bound(999999999);
+/// assert!(bound >= 22801763489 && bound < 22830000000);
+/// ```
+pub fn nth_prime_upper_bound(n: u64) -> u64 {
+    if (n as usize) < FIRST_PRIMES.len() {
+        return FIRST_PRIMES[n as usize];
+    }
+
+    let k = n as f64 + 1.0;
+    let (ln, ln_ln) = (k.ln(), k.ln().ln());
+    let bound = if k >= 688383.0 {
+        k * (ln + ln_ln - 1.0 + (ln_ln - 2.0) / ln)
+    } else if k >= 39017.0 {
+        k * (ln + ln_ln - 0.9484)
+    } else {
+        k * (ln + ln_ln)
+    };
+    round_up(bound)
+}
+
+/// Returns a lower bound for the `n`th prime, indexed from 0 as in `nth_prime`.
+///
+/// Writing `k = n + 1` and `L = ln(k)`, this uses Dusart's bound
+/// `k (L + ln(L) - 1 + (ln(L) - 2.1) / L)`, which holds for `k >= 3`.
+///
+/// # Examples
+///
+/// ```
+/// use primesieve::{nth_prime, nth_prime_lower_bound};
+///
+/// assert_eq!(nth_prime_lower_bound(0), 2);
+/// assert!(nth_prime_lower_bound(99) <= nth_prime(99));
+///
+/// let bound = nth_prime_lower_bound(999999999);
+/// assert!(bound <= 22801763489 && bound > 22790000000);
+/// ```
+pub fn nth_prime_lower_bound(n: u64) -> u64 {
+    if (n as usize) < FIRST_PRIMES.len() {
+        return FIRST_PRIMES[n as usize];
+    }
+
+    let k = n as f64 + 1.0;
+    let (ln, ln_ln) = (k.ln(), k.ln().ln());
+    round_down(k * (ln + ln_ln - 1.0 + (ln_ln - 2.1) / ln))
+}
+
+/// Returns an upper bound for the number of primes up to and including `x`.
+///
+/// Writing `L = ln(x)`, this uses Dusart's bound `x / L (1 + 1 / L + 2.51 / L^2)` for
+/// `x >= 355991`, and his bound `x / L (1 + 1.2762 / L)` for smaller `x`, with the first few
+/// cases counted exactly.
+///
+/// # Examples
+///
+/// ```
+/// use primesieve::prime_pi_upper_bound;
+///
+/// assert_eq!(prime_pi_upper_bound(10), 4);
+///
+/// let bound = prime_pi_upper_bound(1000000000000);
+/// assert!(bound >= 37607912018 && bound < 37700000000);
+/// ```
+pub fn prime_pi_upper_bound(x: u64) -> u64 {
+    if x <= *FIRST_PRIMES.last().unwrap() {
+        return FIRST_PRIMES.iter().filter(|&&p| p <= x).count() as u64;
+    }
+
+    let ln = (x as f64).ln();
+    let scale = x as f64 / ln;
+    let bound = if x >= 355991 {
+        scale * (1.0 + 1.0 / ln + 2.51 / (ln * ln))
+    } else {
+        scale * (1.0 + 1.2762 / ln)
+    };
+    round_up(bound)
+}
+
+/// Returns a lower bound for the number of primes up to and including `x`.
+///
+/// Writing `L = ln(x)`, this uses Dusart's bound `x / L (1 + 1 / L)` for `x >= 599`, and the
+/// bound `x / L` for `x >= 17`, with the first few cases counted exactly.
+///
+/// # Examples
+///
+/// ```
+/// use primesieve::prime_pi_lower_bound;
+///
+/// assert_eq!(prime_pi_lower_bound(10), 4);
+///
+/// let bound = prime_pi_lower_bound(1000000000000);
+/// assert!(bound <= 37607912018 && bound > 37500000000);
+/// ```
+pub fn prime_pi_lower_bound(x: u64) -> u64 {
+    if x <= *FIRST_PRIMES.last().unwrap() {
+        return FIRST_PRIMES.iter().filter(|&&p| p <= x).count() as u64;
+    }
+
+    let ln = (x as f64).ln();
+    let scale = x as f64 / ln;
+    let bound = if x >= 599 { scale * (1.0 + 1.0 / ln) } else { scale };
+    round_down(bound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lmo::prime_pi;
+    use nth::nth_prime;
+    use sieve::Sieve;
+
+    #[test]
+    fn test_small_values() {
+        let limit = 20000000;
+        let sieve = Sieve::to_limit(limit);
+        let mut count = 0;
+        let mut primes = sieve.iter().take_while(|&p| p <= limit).peekable();
+        for x in 0..=limit {
+            if primes.peek() == Some(&x) {
+                let p = primes.next().unwrap();
+                assert!(nth_prime_lower_bound(count) <= p && p <= nth_prime_upper_bound(count));
+                count += 1;
+            }
+            assert!(prime_pi_lower_bound(x) <= count && count <= prime_pi_upper_bound(x));
+        }
+    }
+
+    #[test]
+    fn test_large_values() {
+        let mut x = 100000000;
+        while x <= 10000000000 {
+            let pi = prime_pi(x);
+            assert!(prime_pi_lower_bound(x) <= pi && pi <= prime_pi_upper_bound(x));
+            let p = nth_prime(x);
+            assert!(nth_prime_lower_bound(x) <= p && p <= nth_prime_upper_bound(x));
+            x *= 10;
+        }
+    }
+
+    #[test]
+    fn test_saturates() {
+        assert_eq!(nth_prime_upper_bound(u64::MAX - 1), u64::MAX);
+        assert_eq!(nth_prime_upper_bound(u64::MAX), u64::MAX);
+        assert_eq!(nth_prime_lower_bound(u64::MAX), u64::MAX);
+        assert!(nth_prime_lower_bound(1 << 40) > 1 << 40);
+        assert!(prime_pi_upper_bound(u64::MAX) < u64::MAX / 40);
+    }
+}
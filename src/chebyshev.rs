@@ -0,0 +1,93 @@
+//! Chebyshev's functions `θ(x)` and `ψ(x)`, which weight each prime by its logarithm.
+
+use iterator::{SieveIterator, SMALL_PRIMES};
+use segsieve::SegmentedSieve;
+use sieve::Sieve;
+
+/// Returns Chebyshev's function `θ(x)`, the sum of `ln(p)` over the primes `p <= x`.
+///
+/// The primes are found with a segmented sieve, and the logarithms are summed separately for each
+/// segment before being added to the total, which keeps the rounding error small even when there
+/// are billions of primes.
+///
+/// # Examples
+///
+/// ```
+/// let theta = primesieve::chebyshev_theta(100);
+/// assert!((theta - 83.728390399063934).abs() < 1e-12);
+///
+/// let theta = primesieve::chebyshev_theta(1000000);
+/// assert!((theta - 998484.17502563429).abs() < 1e-6);
+/// ```
+pub fn chebyshev_theta(x: u64) -> f64 {
+    let mut theta = SMALL_PRIMES.iter().filter(|&&p| p <= x).map(|&p| (p as f64).ln()).sum();
+
+    let mut sieve = SegmentedSieve::new(0, x.saturating_add(1));
+    while sieve.advance() {
+        let primes = SieveIterator::with_offset(sieve.segment(), sieve.segment_low());
+        theta += primes.take_while(|&p| p <= x).map(|p| (p as f64).ln()).sum::<f64>();
+    }
+
+    theta
+}
+
+/// Returns Chebyshev's function `ψ(x)`, the sum of `ln(p)` over the prime powers `p^k <= x`.
+///
+/// This is `θ(x)` together with an extra `ln(p)` for each higher power of each prime up to the
+/// square root of `x`.
+///
+/// # Examples
+///
+/// ```
+/// let psi = primesieve::chebyshev_psi(100);
+/// assert!((psi - 94.045311229357400).abs() < 1e-12);
+///
+/// // By the prime number theorem, `ψ(x)` is close to `x`.
+/// let x = 10000000.0;
+/// assert!((primesieve::chebyshev_psi(x as u64) - x).abs() < x.sqrt());
+/// ```
+pub fn chebyshev_psi(x: u64) -> f64 {
+    let sqrt = (x as f64).sqrt() as u64 + 1;
+    let sieve = Sieve::to_limit(sqrt);
+
+    let mut powers = 0.0;
+    for p in sieve.iter().take_while(|&p| p <= x / p) {
+        // Count the powers `p^k <= x` with `k >= 2`.
+        let mut power = p * p;
+        let mut count = 1;
+        while let Some(next) = power.checked_mul(p).filter(|&next| next <= x) {
+            power = next;
+            count += 1;
+        }
+        powers += count as f64 * (p as f64).ln();
+    }
+
+    chebyshev_theta(x) + powers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_sieve() {
+        let limit = 10000000;
+        let sieve = Sieve::to_limit(limit);
+        let primes = sieve.iter().take_while(|&p| p <= limit).collect::<Vec<u64>>();
+        for &x in &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 1000, 65536, 7864319, 7864320, limit] {
+            let theta = primes.iter().take_while(|&&p| p <= x).map(|&p| (p as f64).ln());
+            let theta = theta.sum::<f64>();
+            let mut psi = 0.0;
+            for &p in primes.iter().take_while(|&&p| p <= x) {
+                let mut power = p;
+                while power <= x {
+                    psi += (p as f64).ln();
+                    power *= p;
+                }
+            }
+
+            assert!((chebyshev_theta(x) - theta).abs() <= 1e-9 * theta.max(1.0));
+            assert!((chebyshev_psi(x) - psi).abs() <= 1e-9 * psi.max(1.0));
+        }
+    }
+}
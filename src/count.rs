@@ -0,0 +1,50 @@
+//! Counting primes in an interval without storing them.
+
+use iterator::SMALL_PRIMES;
+use segment::count_below;
+use segsieve::SegmentedSieve;
+
+/// Returns the number of primes in the interval `[lo, hi)`.
+///
+/// This runs a segmented sieve over the interval and counts the primes in each segment as it
+/// goes, throwing the segment away afterwards, so that only enough memory is needed for the
+/// sieving primes up to the square root of `hi`.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(primesieve::count_primes(0, 100), 25);
+/// assert_eq!(primesieve::count_primes(0, 1000000), 78498);
+/// assert_eq!(primesieve::count_primes(1000000000000, 1000000000100), 4);
+/// ```
+pub fn count_primes(lo: u64, hi: u64) -> u64 {
+    if hi <= lo {
+        return 0;
+    }
+
+    let mut count = SMALL_PRIMES.iter().filter(|&&p| lo <= p && p < hi).count() as u64;
+    let mut sieve = SegmentedSieve::new(lo, hi);
+    while sieve.advance() {
+        // The last segment may contain some primes beyond the end of the interval.
+        count += count_below(sieve.segment(), hi - sieve.segment_low());
+    }
+
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sieve::Sieve;
+
+    #[test]
+    fn test_count_primes() {
+        let sieve = Sieve::to_limit(20000000);
+        let primes = sieve.iter().take_while(|&p| p < 20000000).collect::<Vec<u64>>();
+        for &(lo, hi) in &[(0, 0), (0, 2), (0, 3), (3, 6), (5, 7), (1000, 1000000),
+                           (12345, 16777216), (7864320, 20000000)] {
+            let expected = primes.iter().filter(|&&p| lo <= p && p < hi).count() as u64;
+            assert_eq!(count_primes(lo, hi), expected);
+        }
+    }
+}
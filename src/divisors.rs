@@ -0,0 +1,121 @@
+//! Enumerating the divisors of a number from its factorisation.
+
+/// A lazy iterator over the divisors of a number, built from its factorisation.
+///
+/// The divisors are found by counting through the exponents of the primes as the digits of a
+/// mixed-radix number, so only the current exponents are held in memory and each divisor costs
+/// an amortised constant number of multiplications. The divisors are not yielded in increasing
+/// order, but 1 always comes first.
+///
+/// # Examples
+///
+/// ```
+/// use primesieve::DivisorIterator;
+///
+/// let divisors = DivisorIterator::new(&[(2, 2), (3, 1)]).collect::<Vec<u64>>();
+/// assert_eq!(divisors, vec![1, 2, 4, 3, 6, 12]);
+///
+/// let unitary = DivisorIterator::unitary(&[(2, 2), (3, 1)]).collect::<Vec<u64>>();
+/// assert_eq!(unitary, vec![1, 4, 3, 12]);
+///
+/// let squarefree = DivisorIterator::squarefree(&[(2, 2), (3, 1)]).collect::<Vec<u64>>();
+/// assert_eq!(squarefree, vec![1, 2, 3, 6]);
+/// ```
+pub struct DivisorIterator {
+    /// For each digit, the factor by which the divisor changes when the digit is incremented,
+    /// the largest value of the digit, and the factor by which the divisor changes when the
+    /// digit is reset from its largest value to 0.
+    radices: Vec<(u64, u64, u64)>,
+    /// The current value of each digit.
+    digits: Vec<u64>,
+    /// The next divisor to yield, or `None` if all of them have been yielded.
+    current: Option<u64>,
+}
+
+impl DivisorIterator {
+    /// Create an iterator from the given step and largest exponent for each prime.
+    fn from_steps<I: Iterator<Item = (u64, u64)>>(steps: I) -> DivisorIterator {
+        let radices = steps
+            .map(|(step, max)| (step, max, step.pow(max as u32)))
+            .collect::<Vec<_>>();
+        DivisorIterator {
+            digits: vec![0; radices.len()],
+            radices,
+            current: Some(1),
+        }
+    }
+
+    /// Create an iterator over all of the divisors of the number with the given factorisation,
+    /// given as (prime, exponent) pairs.
+    pub fn new(factors: &[(u64, u64)]) -> DivisorIterator {
+        DivisorIterator::from_steps(factors.iter().cloned())
+    }
+
+    /// Create an iterator over the unitary divisors of the number with the given factorisation,
+    /// that is the divisors `d` of `n` for which `d` and `n / d` are coprime.
+    pub fn unitary(factors: &[(u64, u64)]) -> DivisorIterator {
+        DivisorIterator::from_steps(factors.iter().map(|&(p, k)| (p.pow(k as u32), 1)))
+    }
+
+    /// Create an iterator over the squarefree divisors of the number with the given
+    /// factorisation.
+    pub fn squarefree(factors: &[(u64, u64)]) -> DivisorIterator {
+        DivisorIterator::from_steps(factors.iter().map(|&(p, _)| (p, 1)))
+    }
+}
+
+impl Iterator for DivisorIterator {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let divisor = self.current?;
+
+        // Increment the mixed-radix number, carrying into the next digit whenever a digit
+        // overflows. If every digit overflows, then all the divisors have been found.
+        self.current = None;
+        let mut next = divisor;
+        for (digit, &(step, max, full)) in self.digits.iter_mut().zip(self.radices.iter()) {
+            if *digit < max {
+                *digit += 1;
+                self.current = Some(next * step);
+                break;
+            }
+            *digit = 0;
+            next /= full;
+        }
+
+        Some(divisor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arith::gcd;
+    use sieve::Sieve;
+
+    /// Collects the divisors yielded by an iterator into increasing order.
+    fn sorted(iter: DivisorIterator) -> Vec<u64> {
+        let mut divisors = iter.collect::<Vec<u64>>();
+        divisors.sort();
+        divisors
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let sieve = Sieve::to_limit(100);
+        let is_squarefree = |d: u64| (2..d).all(|m| d % (m * m) != 0);
+        for n in 1..2000u64 {
+            let factorisation = sieve.factorise(n).unwrap();
+            let factors = factorisation.factors();
+            let divisors = (1..=n).filter(|&d| n % d == 0).collect::<Vec<u64>>();
+            let unitary = divisors.iter().cloned().filter(|&d| gcd(d, n / d) == 1);
+            let squarefree = divisors.iter().cloned().filter(|&d| is_squarefree(d));
+
+            assert_eq!(sorted(DivisorIterator::new(factors)), divisors);
+            assert_eq!(sorted(DivisorIterator::unitary(factors)), unitary.collect::<Vec<_>>());
+            assert_eq!(sorted(DivisorIterator::squarefree(factors)),
+                       squarefree.collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! The errors which can be returned by calculations involving the primes.
+
+use std::error;
+use std::fmt;
+
+/// The ways in which a calculation involving the primes can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Error {
+    /// The calculation was asked about 0, which has no factorisation.
+    Zero,
+    /// The calculation needed a table of prime factors which goes further than the one it was
+    /// given, and could be done with a table extending at least as far as `needed_limit`.
+    ///
+    /// This is only returned by `SpfSieve`, since a `Sieve` can factorise numbers beyond its limit.
+    OutOfRange { needed_limit: u64 },
+    /// The factorisation methods all failed to find a factor of a number known to be composite.
+    ///
+    /// This is only returned by `Sieve::factorise_u128`, and only in the unlikely event that the
+    /// elliptic curve method fails.
+    FactorNotFound { composite: u128 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Zero => write!(f, "0 has no factorisation"),
+            Error::OutOfRange { needed_limit } => {
+                write!(f, "out of range: a limit of at least {} is needed", needed_limit)
+            }
+            Error::FactorNotFound { composite } => {
+                write!(f, "no factor found for the composite number {}", composite)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {}
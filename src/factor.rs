@@ -0,0 +1,575 @@
+//! Factorisation of integers which are too large to be handled by trial division alone, using
+//! Brent's variant of Pollard's rho algorithm for 64-bit integers, and Shanks' square forms
+//! factorisation (SQUFOF) and Lenstra's elliptic curve method (ECM) for 128-bit integers.
+
+use std::cell::OnceCell;
+use std::cmp::min;
+
+use arith::{gcd, isqrt, Montgomery128, Montgomery64};
+use error::Error;
+use primality::{baillie_psw, miller_rabin};
+
+/// The primes used for trial division before resorting to Pollard's rho algorithm, which struggles
+/// with very small factors.
+const TRIAL_DIVISORS: &[u64] = &[2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47];
+
+/// The number of steps of the rho iteration between each gcd computation.
+const BATCH_SIZE: u64 = 128;
+
+/// Numbers below this limit are factorised with SQUFOF rather than ECM.
+const SQUFOF_LIMIT: u128 = 1 << 80;
+
+/// The multipliers tried in turn by SQUFOF.
+const SQUFOF_MULTIPLIERS: &[u128] = &[
+    1, 3, 5, 7, 11, 3 * 5, 3 * 7, 3 * 11, 5 * 7, 5 * 11, 7 * 11, 3 * 5 * 7, 3 * 5 * 11, 3 * 7 * 11,
+    5 * 7 * 11, 3 * 5 * 7 * 11,
+];
+
+/// The stage 1 bounds for ECM, along with the number of curves to try with each bound. These are
+/// the standard choices for finding factors of up to 15, 20, 25 and 30 digits. The smallest prime
+/// factor of a composite `u128` has at most 20 digits, so it is vanishingly unlikely that every
+/// curve fails.
+const ECM_SCHEDULE: &[(u64, usize)] = &[(2000, 25), (11000, 90), (50000, 300), (250000, 700)];
+
+/// The ratio between the stage 2 and stage 1 bounds for ECM.
+const ECM_STAGE_2_RATIO: u64 = 100;
+
+/// The primes needed by ECM are those up to this limit, beyond which stage 2 is cut short.
+pub const ECM_PRIME_LIMIT: u64 = 50000 * ECM_STAGE_2_RATIO;
+
+/// The distance between the giant steps in stage 2 of ECM.
+const ECM_GIANT_STEP: u64 = 2 * 3 * 5 * 7;
+
+/// Returns the index `m` of the giant step in stage 2 of ECM nearest to `n`, so that `n = mD + j`
+/// or `n = mD - j` for some `j <= D / 2`, where `D` is `ECM_GIANT_STEP`.
+fn giant_step_index(n: u64) -> u64 {
+    (n + ECM_GIANT_STEP / 2) / ECM_GIANT_STEP
+}
+
+/// Returns the index of the giant step at which stage 2 of ECM starts, which must be no later
+/// than that of any prime above the stage 1 bound `b1`, since the giant steps only move forwards.
+fn first_giant_step(b1: u64) -> u64 {
+    giant_step_index(b1 + 1)
+}
+
+/// Finds a non-trivial factor of the odd composite number `n`, using Brent's variant of Pollard's
+/// rho algorithm.
+fn pollard_rho(n: u64) -> u64 {
+    let mont = Montgomery64::new(n);
+
+    // Try the polynomials x^2 + c for successive values of c until one gives a proper factor.
+    for c in 1.. {
+        let f = |x: u64| mont.add(mont.mul(x, x), c);
+
+        let (mut x, mut y, mut ys) = (0, 0, 0);
+        let (mut product, mut g, mut r) = (mont.one(), 1, 1);
+        while g == 1 {
+            x = y;
+            for _ in 0..r {
+                y = f(y);
+            }
+
+            // Accumulate the product of the differences, taking a gcd once per batch.
+            let mut k = 0;
+            while k < r && g == 1 {
+                ys = y;
+                for _ in 0..BATCH_SIZE.min(r - k) {
+                    y = f(y);
+                    product = mont.mul(product, x.abs_diff(y));
+                }
+                g = gcd(product, n);
+                k += BATCH_SIZE;
+            }
+            r *= 2;
+        }
+
+        // If the batch overshot, then step through it one at a time to find the factor.
+        if g == n {
+            loop {
+                ys = f(ys);
+                g = gcd(x.abs_diff(ys), n);
+                if g > 1 {
+                    break;
+                }
+            }
+        }
+
+        if g != n {
+            return g;
+        }
+    }
+
+    unreachable!()
+}
+
+/// Pushes the prime factors of `n`, with repetition and in no particular order, to `factors`.
+///
+/// `n` must not be divisible by any of the `TRIAL_DIVISORS`.
+fn split(n: u64, factors: &mut Vec<u64>) {
+    if n == 1 {
+        return;
+    }
+    if miller_rabin(n) {
+        factors.push(n);
+        return;
+    }
+
+    let d = pollard_rho(n);
+    split(d, factors);
+    split(n / d, factors);
+}
+
+/// Factorises `n` into (prime, exponent) pairs, in increasing order of the primes, where `n` is
+/// known to have no prime factors smaller than `smallest`.
+///
+/// Trial division is used for the very smallest primes, after which Pollard's rho algorithm
+/// finishes the job. This is fast for any `u64`, no matter the size of its prime factors.
+pub fn factorise_from(mut n: u64, smallest: u64) -> Vec<(u64, u64)> {
+    debug_assert!(n > 0);
+
+    let mut result = Vec::new();
+    for &p in TRIAL_DIVISORS.iter().filter(|&&p| p >= smallest) {
+        let mut count = 0;
+        while n % p == 0 {
+            n /= p;
+            count += 1;
+        }
+        if count > 0 {
+            result.push((p, count));
+        }
+    }
+
+    let mut factors = Vec::new();
+    split(n, &mut factors);
+    factors.sort_unstable();
+    for p in factors {
+        match result.last_mut() {
+            Some(&mut (q, ref mut count)) if q == p => *count += 1,
+            _ => result.push((p, 1)),
+        }
+    }
+
+    result
+}
+
+/// Returns the integer square root of `n`, for `n` small enough to be handled accurately with the
+/// help of floating-point arithmetic.
+fn isqrt_small(n: u64) -> u64 {
+    let mut r = (n as f64).sqrt() as u64;
+    while r * r > n {
+        r -= 1;
+    }
+    while (r + 1) * (r + 1) <= n {
+        r += 1;
+    }
+    r
+}
+
+/// Attempts to find a non-trivial factor of the odd composite number `n < SQUFOF_LIMIT`, which
+/// is not a perfect square, using Shanks' square forms factorisation.
+fn squfof(n: u128) -> Option<u128> {
+    for &k in SQUFOF_MULTIPLIERS {
+        // The arithmetic below fits into an `i64` as long as `kn` is not too large.
+        let kn = k * n;
+        if kn >= 1 << 92 {
+            break;
+        }
+        let p0 = isqrt(kn) as i64;
+        let q0 = (kn - (p0 as u128) * (p0 as u128)) as i64;
+        if q0 == 0 {
+            continue;
+        }
+
+        // Step forward through the continued fraction expansion of sqrt(kn) until a square form
+        // is found at an even index.
+        let limit = 3 * 2 * isqrt_small(2 * p0 as u64) as i64;
+        let (mut p_prev, mut p, mut q_prev, mut q) = (p0, p0, 1, q0);
+        let mut root = None;
+        for i in 2..limit {
+            let b = (p0 + p) / q;
+            p = b * q - p;
+            let old_q = q;
+            q = q_prev + b * (p_prev - p);
+            let r = isqrt_small(q as u64) as i64;
+            if i % 2 == 0 && r * r == q {
+                root = Some(r);
+                break;
+            }
+            q_prev = old_q;
+            p_prev = p;
+        }
+        let r = match root {
+            Some(r) => r,
+            None => continue,
+        };
+
+        // Step through the reduced form until the value of P repeats.
+        let b = (p0 - p) / r;
+        p += b * r;
+        p_prev = p;
+        q_prev = r;
+        q = ((kn - (p_prev as u128) * (p_prev as u128)) / q_prev as u128) as i64;
+        loop {
+            let b = (p0 + p) / q;
+            p_prev = p;
+            p = b * q - p;
+            let old_q = q;
+            q = q_prev + b * (p_prev - p);
+            q_prev = old_q;
+            if p == p_prev {
+                break;
+            }
+        }
+
+        let factor = gcd(n, q_prev as u128);
+        if factor != 1 && factor != n {
+            return Some(factor);
+        }
+    }
+
+    None
+}
+
+/// A point on a Montgomery curve `By^2 = x^3 + Ax^2 + x`, in projective coordinates with only the
+/// `X` and `Z` coordinates retained, all in Montgomery form.
+#[derive(Clone, Copy)]
+struct Point {
+    x: u128,
+    z: u128,
+}
+
+/// A Montgomery curve modulo `n`, represented by the constant `(A + 2) / 4` in projective form.
+struct Curve<'a> {
+    mont: &'a Montgomery128,
+    a24_num: u128,
+    a24_den: u128,
+}
+
+impl<'a> Curve<'a> {
+    /// Returns the point `2P`.
+    fn double(&self, p: Point) -> Point {
+        let m = self.mont;
+        let sum = m.add(p.x, p.z);
+        let diff = m.sub(p.x, p.z);
+        let t = m.mul(sum, sum);
+        let s = m.mul(diff, diff);
+        let d = m.sub(t, s);
+        let s_den = m.mul(s, self.a24_den);
+        Point {
+            x: m.mul(t, s_den),
+            z: m.mul(d, m.add(s_den, m.mul(self.a24_num, d))),
+        }
+    }
+
+    /// Returns the point `P + Q`, given the point `P - Q`.
+    fn add(&self, p: Point, q: Point, diff: Point) -> Point {
+        let m = self.mont;
+        let u = m.mul(m.sub(p.x, p.z), m.add(q.x, q.z));
+        let v = m.mul(m.add(p.x, p.z), m.sub(q.x, q.z));
+        let plus = m.add(u, v);
+        let minus = m.sub(u, v);
+        Point {
+            x: m.mul(diff.z, m.mul(plus, plus)),
+            z: m.mul(diff.x, m.mul(minus, minus)),
+        }
+    }
+
+    /// Returns the point `kP` for `k > 0`, using the Montgomery ladder.
+    fn multiply(&self, p: Point, k: u64) -> Point {
+        assert!(k > 0, "the point at infinity has no x-only representation");
+        let (mut r0, mut r1) = (p, self.double(p));
+        for bit in (0..63 - k.leading_zeros()).rev() {
+            if (k >> bit) & 1 == 1 {
+                r0 = self.add(r1, r0, p);
+                r1 = self.double(r1);
+            } else {
+                r1 = self.add(r0, r1, p);
+                r0 = self.double(r0);
+            }
+        }
+        r0
+    }
+}
+
+/// Runs both stages of ECM on a single curve, chosen using Suyama's parametrisation with the given
+/// value of `sigma`, returning a non-trivial factor of `n` if one is found.
+fn ecm_curve(mont: &Montgomery128, sigma: u128, b1: u64, primes: &[u64]) -> Option<u128> {
+    let n = mont.modulus();
+    let m = mont;
+    let b2 = min(b1 * ECM_STAGE_2_RATIO, ECM_PRIME_LIMIT);
+
+    // Suyama's parametrisation: u = sigma^2 - 5, v = 4 sigma, starting at the point (u^3 : v^3)
+    // on the curve with (A + 2) / 4 = (v - u)^3 (3u + v) / 16u^3v.
+    let sigma = m.encode(sigma);
+    let u = m.sub(m.mul(sigma, sigma), m.encode(5));
+    let v = m.mul(m.encode(4), sigma);
+    let u3 = m.mul(m.mul(u, u), u);
+    let v_minus_u = m.sub(v, u);
+    let v_minus_u3 = m.mul(m.mul(v_minus_u, v_minus_u), v_minus_u);
+    let curve = Curve {
+        mont,
+        a24_num: m.mul(v_minus_u3, m.add(m.mul(m.encode(3), u), v)),
+        a24_den: m.mul(m.mul(m.encode(16), u3), v),
+    };
+    match gcd(curve.a24_den, n) {
+        1 => {}
+        g if g == n => return None,
+        g => return Some(g),
+    }
+    let mut q = Point { x: u3, z: m.mul(m.mul(v, v), v) };
+
+    // Stage 1: multiply by every prime power up to `b1`.
+    let mut stage_2_primes = primes;
+    for (ix, &p) in primes.iter().enumerate() {
+        if p > b1 {
+            stage_2_primes = &primes[ix..];
+            break;
+        }
+        let mut power = p;
+        while power * p <= b1 {
+            power *= p;
+        }
+        q = curve.multiply(q, power);
+    }
+    match gcd(q.z, n) {
+        1 => {}
+        g if g == n => return None,
+        g => return Some(g),
+    }
+
+    // Stage 2: look for a single prime `p` in `(b1, b2]` such that the order of `Q` is `p`. Each
+    // such prime is `mD +/- j` for some `j < D / 2` coprime to `D`, and then `mDQ` and `jQ`
+    // coincide modulo the factor, so the cross product of their coordinates vanishes.
+    let half_step = ECM_GIANT_STEP / 2;
+    let mut baby_steps = vec![None; half_step as usize];
+    let q2 = curve.double(q);
+    let (mut prev, mut curr) = (q, curve.add(q2, q, q));
+    baby_steps[1] = Some(q);
+    for j in (3..half_step).step_by(2) {
+        if gcd(j, ECM_GIANT_STEP) == 1 {
+            baby_steps[j as usize] = Some(curr);
+        }
+        let next = curve.add(curr, q2, prev);
+        prev = curr;
+        curr = next;
+    }
+
+    let giant_step = curve.multiply(q, ECM_GIANT_STEP);
+    let mut m_curr = first_giant_step(b1);
+    let mut giant_prev = curve.multiply(q, (m_curr - 1) * ECM_GIANT_STEP);
+    let mut giant_curr = curve.multiply(q, m_curr * ECM_GIANT_STEP);
+    let mut product = m.one();
+    for &p in stage_2_primes.iter().take_while(|&&p| p <= b2) {
+        let m_p = giant_step_index(p);
+        while m_curr < m_p {
+            let next = curve.add(giant_curr, giant_step, giant_prev);
+            giant_prev = giant_curr;
+            giant_curr = next;
+            m_curr += 1;
+        }
+
+        let j = p.abs_diff(m_p * ECM_GIANT_STEP);
+        if let Some(baby) = baby_steps[j as usize] {
+            let cross = m.sub(m.mul(giant_curr.x, baby.z), m.mul(baby.x, giant_curr.z));
+            product = m.mul(product, cross);
+        }
+    }
+    match gcd(product, n) {
+        g if g == 1 || g == n => None,
+        g => Some(g),
+    }
+}
+
+/// Finds a non-trivial factor of the odd composite number `n`, which is not a perfect square,
+/// using Lenstra's elliptic curve method with Montgomery curves, or returns `None` if every curve
+/// in `ECM_SCHEDULE` fails. The given primes must include all those up to `ECM_PRIME_LIMIT`.
+fn ecm(n: u128, primes: &[u64]) -> Option<u128> {
+    let mont = Montgomery128::new(n);
+    let mut sigma = 6;
+    for &(b1, curves) in ECM_SCHEDULE {
+        for _ in 0..curves {
+            if let Some(factor) = ecm_curve(&mont, sigma, b1, primes) {
+                return Some(factor);
+            }
+            sigma += 1;
+        }
+    }
+
+    None
+}
+
+/// Pushes the prime factors of `n`, with repetition and in no particular order, to `factors`.
+///
+/// `n` must not be divisible by any of the `TRIAL_DIVISORS`. The primes needed for ECM are only
+/// calculated if they are actually needed. Returns `Err(Error::FactorNotFound { .. })` if ECM
+/// fails to split a composite factor of `n`.
+fn split_u128<F>(n: u128, factors: &mut Vec<u128>, primes: &OnceCell<Vec<u64>>, ecm_primes: &F)
+                 -> Result<(), Error>
+where
+    F: Fn() -> Vec<u64>,
+{
+    if n <= u64::MAX as u128 {
+        let mut small_factors = Vec::new();
+        split(n as u64, &mut small_factors);
+        factors.extend(small_factors.into_iter().map(u128::from));
+        return Ok(());
+    }
+    if baillie_psw(n) {
+        factors.push(n);
+        return Ok(());
+    }
+
+    let root = isqrt(n);
+    let d = if root * root == n {
+        Some(root)
+    } else if n < SQUFOF_LIMIT {
+        squfof(n).or_else(|| ecm(n, primes.get_or_init(ecm_primes)))
+    } else {
+        ecm(n, primes.get_or_init(ecm_primes))
+    };
+    let d = d.ok_or(Error::FactorNotFound { composite: n })?;
+    split_u128(d, factors, primes, ecm_primes)?;
+    split_u128(n / d, factors, primes, ecm_primes)
+}
+
+/// Factorises `n` into (prime, exponent) pairs, in increasing order of the primes, where `n` is
+/// known to have no prime factors smaller than `smallest`.
+///
+/// Trial division is used for the very smallest primes, after which the factorisation is
+/// finished using Pollard's rho algorithm, SQUFOF or ECM, depending on the size of what is left.
+/// The function `ecm_primes` is called at most once, if ECM is needed, and must return all the
+/// primes up to `ECM_PRIME_LIMIT`. Returns `Err(Error::FactorNotFound { .. })` in the unlikely
+/// event that ECM fails to split a composite factor of `n`.
+pub fn factorise_u128_from<F>(mut n: u128, smallest: u64, ecm_primes: F)
+                              -> Result<Vec<(u128, u64)>, Error>
+where
+    F: Fn() -> Vec<u64>,
+{
+    debug_assert!(n > 0);
+
+    let mut result = Vec::new();
+    for &p in TRIAL_DIVISORS.iter().filter(|&&p| p >= smallest) {
+        let mut count = 0;
+        while n % p as u128 == 0 {
+            n /= p as u128;
+            count += 1;
+        }
+        if count > 0 {
+            result.push((p as u128, count));
+        }
+    }
+
+    let mut factors = Vec::new();
+    split_u128(n, &mut factors, &OnceCell::new(), &ecm_primes)?;
+    factors.sort_unstable();
+    for p in factors {
+        match result.last_mut() {
+            Some(&mut (q, ref mut count)) if q == p => *count += 1,
+            _ => result.push((p, 1)),
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sieve::Sieve;
+
+    /// Factorises `n` by trial division, for checking the results of `factorise_from`.
+    fn naive_factorise(mut n: u64) -> Vec<(u64, u64)> {
+        let mut result = Vec::new();
+        let mut p = 2;
+        while p * p <= n {
+            let mut count = 0;
+            while n % p == 0 {
+                n /= p;
+                count += 1;
+            }
+            if count > 0 {
+                result.push((p, count));
+            }
+            p += 1;
+        }
+        if n > 1 {
+            result.push((n, 1));
+        }
+        result
+    }
+
+    #[test]
+    fn test_small_values() {
+        for n in 1..100000 {
+            assert_eq!(factorise_from(n, 2), naive_factorise(n));
+        }
+    }
+
+    /// Returns the primes needed for ECM.
+    fn ecm_primes() -> Vec<u64> {
+        Sieve::to_limit(ECM_PRIME_LIMIT).iter().take_while(|&p| p <= ECM_PRIME_LIMIT).collect()
+    }
+
+    #[test]
+    fn test_squfof() {
+        let cases = [(1000003u128, 1000033), (4294967291, 4294967279),
+                     ((1 << 61) - 1, (1 << 17) - 1), (1099511627791, 1099511627689)];
+        for &(p, q) in &cases {
+            let factor = squfof(p * q).unwrap();
+            assert!(factor == p || factor == q);
+        }
+    }
+
+    #[test]
+    fn test_ecm() {
+        let primes = ecm_primes();
+        let cases = [(4294967291u128, 309485009821345068724781063),
+                     (1125899906842679, 1180591620717411303449)];
+        for &(p, q) in &cases {
+            let factor = ecm(p * q, &primes).unwrap();
+            assert!(factor == p || factor == q);
+        }
+    }
+
+    #[test]
+    fn test_ecm_stage_2() {
+        let primes = ecm_primes();
+        for &(b1, _) in ECM_SCHEDULE {
+            let first = primes.iter().cloned().find(|&p| p > b1).unwrap();
+            assert!(first_giant_step(b1) <= giant_step_index(first));
+        }
+
+        // The point found on this curve modulo 1054957 has order 11003 after stage 1, which is
+        // the first prime beyond the stage 1 bound of 11000.
+        let (p, q) = (1054957u128, 309485009821345068724781063);
+        let mont = Montgomery128::new(p * q);
+        assert_eq!(ecm_curve(&mont, 9, 11000, &primes), Some(p));
+    }
+
+    #[test]
+    fn test_factorise_u128() {
+        let p = 18446744073709551557;
+        let q = 1099511627791;
+        assert_eq!(factorise_u128_from(p * q * 6, 2, ecm_primes),
+                   Ok(vec![(2, 1), (3, 1), (q, 1), (p, 1)]));
+        assert_eq!(factorise_u128_from(p * p, 2, ecm_primes), Ok(vec![(p, 2)]));
+        assert_eq!(factorise_u128_from(1 << 127, 2, ecm_primes), Ok(vec![(2, 127)]));
+        assert_eq!(factorise_u128_from(u128::MAX, 2, ecm_primes),
+                   Ok(vec![(3, 1), (5, 1), (17, 1), (257, 1), (641, 1), (65537, 1), (274177, 1),
+                           (6700417, 1), (67280421310721, 1)]));
+    }
+
+    #[test]
+    fn test_large_values() {
+        let p = 4294967291;
+        let q = 4294967279;
+        assert_eq!(factorise_from(p * q, 2), vec![(q, 1), (p, 1)]);
+        assert_eq!(factorise_from(65537 * 65537 * 65537 * 65521, 2), vec![(65521, 1), (65537, 3)]);
+        assert_eq!(factorise_from(1 << 63, 2), vec![(2, 63)]);
+        assert_eq!(factorise_from(18446744073709551557, 2), vec![(18446744073709551557, 1)]);
+        assert_eq!(factorise_from(u64::MAX, 2),
+                   vec![(3, 1), (5, 1), (17, 1), (257, 1), (641, 1), (65537, 1), (6700417, 1)]);
+        assert_eq!(factorise_from(1000000000000000003 * 17, 17),
+                   vec![(17, 1), (1000000000000000003, 1)]);
+    }
+}
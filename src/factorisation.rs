@@ -0,0 +1,253 @@
+//! A structured representation of the factorisation of a positive integer.
+
+use std::cmp::{max, min, Ordering};
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::Mul;
+use std::slice;
+use std::vec;
+
+use divisors::DivisorIterator;
+
+/// The factorisation of a positive integer into prime powers.
+///
+/// The factors are held as (prime, exponent) pairs, in increasing order of the primes and with
+/// every exponent positive, so the factorisation of 1 is empty. Factorisations can be multiplied
+/// together, and their gcd and lcm are found by comparing exponents, without needing to know the
+/// values of the numbers they represent.
+///
+/// # Examples
+///
+/// ```
+/// let sieve = primesieve::Sieve::to_limit(100);
+/// let a = sieve.factorise(360).unwrap();
+/// let b = sieve.factorise(84).unwrap();
+///
+/// assert_eq!(a.to_string(), "2^3 * 3^2 * 5");
+/// assert_eq!(a.factors(), &[(2, 3), (3, 2), (5, 1)]);
+/// assert_eq!((&a * &b).value(), 360 * 84);
+/// assert_eq!(a.gcd(&b).to_string(), "2^2 * 3");
+/// assert_eq!(a.lcm(&b).value(), 2520);
+/// assert_eq!(a.radical().value(), 30);
+/// assert!(!a.is_squarefree());
+///
+/// let factors = vec![(7, 1), (2, 2), (7, 1)].into_iter().collect::<primesieve::Factorisation>();
+/// assert_eq!(factors.to_string(), "2^2 * 7^2");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Factorisation {
+    /// The (prime, exponent) pairs, in increasing order of the primes.
+    factors: Vec<(u64, u64)>,
+}
+
+impl Factorisation {
+    /// Returns the factorisation of 1, which has no prime factors.
+    pub fn one() -> Factorisation {
+        Factorisation { factors: Vec::new() }
+    }
+
+    /// Returns the (prime, exponent) pairs making up this factorisation, in increasing order of
+    /// the primes.
+    pub fn factors(&self) -> &[(u64, u64)] {
+        &self.factors
+    }
+
+    /// Returns an iterator over the (prime, exponent) pairs making up this factorisation.
+    pub fn iter(&self) -> slice::Iter<'_, (u64, u64)> {
+        self.factors.iter()
+    }
+
+    /// Returns the number of distinct primes in this factorisation.
+    pub fn len(&self) -> usize {
+        self.factors.len()
+    }
+
+    /// Returns whether or not this is the factorisation of 1.
+    pub fn is_empty(&self) -> bool {
+        self.factors.is_empty()
+    }
+
+    /// Returns the number represented by this factorisation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number does not fit in a `u64`.
+    pub fn value(&self) -> u64 {
+        self.checked_value().expect("factorisation does not fit in a u64")
+    }
+
+    /// Returns the number represented by this factorisation, or `None` if it does not fit in a
+    /// `u64`.
+    pub fn checked_value(&self) -> Option<u64> {
+        self.factors.iter().try_fold(1u64, |value, &(p, k)| {
+            value.checked_mul(p.checked_pow(u32::try_from(k).ok()?)?)
+        })
+    }
+
+    /// Returns whether or not the number represented by this factorisation is squarefree.
+    pub fn is_squarefree(&self) -> bool {
+        self.factors.iter().all(|&(_, k)| k == 1)
+    }
+
+    /// Returns the factorisation of the radical of this number, the product of its distinct
+    /// prime factors.
+    pub fn radical(&self) -> Factorisation {
+        Factorisation { factors: self.factors.iter().map(|&(p, _)| (p, 1)).collect() }
+    }
+
+    /// Returns the factorisation of the greatest common divisor of two numbers.
+    pub fn gcd(&self, other: &Factorisation) -> Factorisation {
+        self.merge(other, min)
+    }
+
+    /// Returns the factorisation of the least common multiple of two numbers.
+    pub fn lcm(&self, other: &Factorisation) -> Factorisation {
+        self.merge(other, max)
+    }
+
+    /// Returns a lazy iterator over the divisors of the number represented by this factorisation.
+    pub fn divisors(&self) -> DivisorIterator {
+        DivisorIterator::new(&self.factors)
+    }
+
+    /// Combines two factorisations by applying `f` to the exponents of each prime in either of
+    /// them, treating a missing prime as having exponent 0.
+    fn merge<F: Fn(u64, u64) -> u64>(&self, other: &Factorisation, f: F) -> Factorisation {
+        let (mut lhs, mut rhs) = (self.factors.iter().peekable(), other.factors.iter().peekable());
+        let mut factors = Vec::with_capacity(self.len() + other.len());
+        loop {
+            let (p, k) = match (lhs.peek(), rhs.peek()) {
+                (Some(&&(p, j)), Some(&&(q, k))) => match p.cmp(&q) {
+                    Ordering::Less => { lhs.next(); (p, f(j, 0)) }
+                    Ordering::Greater => { rhs.next(); (q, f(0, k)) }
+                    Ordering::Equal => { lhs.next(); rhs.next(); (p, f(j, k)) }
+                },
+                (Some(&&(p, j)), None) => { lhs.next(); (p, f(j, 0)) }
+                (None, Some(&&(q, k))) => { rhs.next(); (q, f(0, k)) }
+                (None, None) => break,
+            };
+            if k > 0 {
+                factors.push((p, k));
+            }
+        }
+        Factorisation { factors }
+    }
+}
+
+impl fmt::Display for Factorisation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.factors.is_empty() {
+            return write!(f, "1");
+        }
+        for (idx, &(p, k)) in self.factors.iter().enumerate() {
+            if idx > 0 {
+                write!(f, " * ")?;
+            }
+            match k {
+                1 => write!(f, "{}", p)?,
+                _ => write!(f, "{}^{}", p, k)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromIterator<(u64, u64)> for Factorisation {
+    /// Collects (prime, exponent) pairs into a factorisation, in any order. Repeated primes have
+    /// their exponents added together, and primes with exponent 0 are dropped.
+    fn from_iter<I: IntoIterator<Item = (u64, u64)>>(iter: I) -> Factorisation {
+        let mut pairs = iter.into_iter().filter(|&(_, k)| k > 0).collect::<Vec<_>>();
+        pairs.sort_unstable();
+
+        let mut factors: Vec<(u64, u64)> = Vec::with_capacity(pairs.len());
+        for (p, k) in pairs {
+            match factors.last_mut() {
+                Some(&mut (q, ref mut count)) if q == p => *count += k,
+                _ => factors.push((p, k)),
+            }
+        }
+        Factorisation { factors }
+    }
+}
+
+impl IntoIterator for Factorisation {
+    type Item = (u64, u64);
+    type IntoIter = vec::IntoIter<(u64, u64)>;
+
+    fn into_iter(self) -> vec::IntoIter<(u64, u64)> {
+        self.factors.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Factorisation {
+    type Item = &'a (u64, u64);
+    type IntoIter = slice::Iter<'a, (u64, u64)>;
+
+    fn into_iter(self) -> slice::Iter<'a, (u64, u64)> {
+        self.factors.iter()
+    }
+}
+
+impl<'a> Mul<&'a Factorisation> for &'a Factorisation {
+    type Output = Factorisation;
+
+    fn mul(self, other: &Factorisation) -> Factorisation {
+        self.merge(other, |j, k| j + k)
+    }
+}
+
+impl Mul for Factorisation {
+    type Output = Factorisation;
+
+    fn mul(self, other: Factorisation) -> Factorisation {
+        &self * &other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arith::gcd;
+    use sieve::Sieve;
+
+    #[test]
+    fn test_display() {
+        let display = |factors: Vec<(u64, u64)>| factors.into_iter().collect::<Factorisation>();
+        assert_eq!(Factorisation::one().to_string(), "1");
+        assert_eq!(display(vec![(2, 1)]).to_string(), "2");
+        assert_eq!(display(vec![(5, 1), (3, 2), (2, 3)]).to_string(), "2^3 * 3^2 * 5");
+        assert_eq!(display(vec![(3, 0), (7, 4)]).to_string(), "7^4");
+    }
+
+    #[test]
+    fn test_operations() {
+        let sieve = Sieve::to_limit(100);
+        for a in 1..200u64 {
+            let fa = sieve.factorise(a).unwrap();
+            assert_eq!(fa.value(), a);
+            assert_eq!(fa.is_squarefree(), (2..a).all(|m| a % (m * m) != 0));
+            assert_eq!(fa.radical().value(), fa.iter().map(|&(p, _)| p).product::<u64>());
+
+            for b in 1..200u64 {
+                let fb = sieve.factorise(b).unwrap();
+                let g = gcd(a, b);
+                assert_eq!(&fa * &fb, sieve.factorise(a * b).unwrap());
+                assert_eq!(fa.gcd(&fb), sieve.factorise(g).unwrap());
+                assert_eq!(fa.lcm(&fb), sieve.factorise(a / g * b).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_checked_value() {
+        let big = vec![(2, 63), (3, 1)].into_iter().collect::<Factorisation>();
+        assert_eq!(big.checked_value(), None);
+        let big = vec![(2, 64)].into_iter().collect::<Factorisation>();
+        assert_eq!(big.checked_value(), None);
+        let huge = vec![(2, 1 << 32)].into_iter().collect::<Factorisation>();
+        assert_eq!(huge.checked_value(), None);
+        let fits = vec![(2, 63)].into_iter().collect::<Factorisation>();
+        assert_eq!(fits.checked_value(), Some(1 << 63));
+    }
+}
@@ -0,0 +1,181 @@
+//! Factorising every integer in an interval, using a segmented sieve which records the prime
+//! divisors of each number rather than just crossing them off.
+
+use std::cmp::min;
+use std::mem;
+
+use factorisation::Factorisation;
+use iterator::SMALL_PRIMES;
+use segsieve::sieving_primes;
+
+/// The number of integers handled in each segment.
+const SEGMENT_SIZE: u64 = 1 << 18;
+
+/// The state of a segmented sieve which finds the prime power divisors of each number in an
+/// interval, one segment at a time.
+///
+/// For each segment, every prime up to the square root of the end of the interval is used to
+/// divide out its full power from each of its multiples in the segment, and whatever remains of
+/// each number after that is either 1 or a single large prime.
+pub struct SegmentFactoriser {
+    /// The primes up to the square root of the end of the interval.
+    primes: Vec<u64>,
+    /// The next multiple of each prime which has not yet been dealt with.
+    next_multiples: Vec<u64>,
+    /// The part of each number in the current segment which is still to be factorised.
+    remaining: Vec<u64>,
+    /// The start of the next segment.
+    low: u64,
+    /// The end of the interval.
+    high: u64,
+}
+
+impl SegmentFactoriser {
+    /// Create a new `SegmentFactoriser` for the numbers in the interval `[lo, hi)`, excluding 0.
+    pub fn new(lo: u64, hi: u64) -> SegmentFactoriser {
+        let lo = lo.max(1);
+        let primes = SMALL_PRIMES.iter().cloned().chain(sieving_primes(hi)).collect::<Vec<_>>();
+        let next_multiples = primes.iter().map(|&p| lo.div_ceil(p) * p).collect();
+
+        SegmentFactoriser {
+            primes,
+            next_multiples,
+            remaining: Vec::new(),
+            low: lo,
+            high: hi,
+        }
+    }
+
+    /// Returns the number of integers in the next segment.
+    pub fn next_segment_len(&self) -> usize {
+        min(self.high.saturating_sub(self.low), SEGMENT_SIZE) as usize
+    }
+
+    /// Factorises the numbers in the next segment, calling `f(idx, p, k)` for each number
+    /// `low + idx` in the segment and each prime `p` which divides it exactly `k` times.
+    ///
+    /// For each number, the primes are given in increasing order. Returns the range `[low, high)`
+    /// of numbers in the segment, or `None` if the whole interval has been dealt with.
+    pub fn next_segment<F>(&mut self, mut f: F) -> Option<(u64, u64)>
+    where
+        F: FnMut(usize, u64, u64),
+    {
+        if self.low >= self.high {
+            return None;
+        }
+        let low = self.low;
+        let high = low + self.next_segment_len() as u64;
+
+        self.remaining.clear();
+        self.remaining.extend(low..high);
+
+        for (&p, next) in self.primes.iter().zip(self.next_multiples.iter_mut()) {
+            let mut multiple = *next;
+            while multiple < high {
+                let idx = (multiple - low) as usize;
+                let mut count = 0;
+                while self.remaining[idx] % p == 0 {
+                    self.remaining[idx] /= p;
+                    count += 1;
+                }
+                f(idx, p, count);
+                multiple += p;
+            }
+            *next = multiple;
+        }
+
+        // Anything left over must be a prime larger than any of the sieving primes.
+        for (idx, &rem) in self.remaining.iter().enumerate() {
+            if rem > 1 {
+                f(idx, rem, 1);
+            }
+        }
+
+        self.low = high;
+        Some((low, high))
+    }
+}
+
+/// An iterator over the factorisations of the integers in an interval, in increasing order.
+///
+/// The factorisations are found using a segmented sieve, so that the memory used is proportional
+/// to the square root of the end of the interval, and each factorisation is given in the same
+/// way as by `Sieve::factorise`.
+///
+/// # Examples
+///
+/// ```
+/// let mut factorisations = primesieve::Factorisations::new(10, 13)
+///     .map(|(n, factors)| (n, factors.to_string()));
+/// assert_eq!(factorisations.next(), Some((10, "2 * 5".to_string())));
+/// assert_eq!(factorisations.next(), Some((11, "11".to_string())));
+/// assert_eq!(factorisations.next(), Some((12, "2^2 * 3".to_string())));
+/// assert_eq!(factorisations.next(), None);
+///
+/// let mut big = primesieve::Factorisations::new(1000000000000, 1000000000001);
+/// let (n, factors) = big.next().unwrap();
+/// assert_eq!((n, factors.factors()), (1000000000000, &[(2, 12), (5, 12)][..]));
+/// ```
+pub struct Factorisations {
+    /// The sieve which factorises the numbers in each segment.
+    factoriser: SegmentFactoriser,
+    /// The factorisations of the numbers in the current segment.
+    factors: Vec<Vec<(u64, u64)>>,
+    /// The first number in the current segment.
+    low: u64,
+    /// The index in the current segment of the next number to yield.
+    idx: usize,
+}
+
+impl Factorisations {
+    /// Create a new `Factorisations` iterator over the integers in the interval `[lo, hi)`. The
+    /// number 0, which has no factorisation, is skipped.
+    pub fn new(lo: u64, hi: u64) -> Factorisations {
+        Factorisations {
+            factoriser: SegmentFactoriser::new(lo, hi),
+            factors: Vec::new(),
+            low: 0,
+            idx: 0,
+        }
+    }
+}
+
+impl Iterator for Factorisations {
+    type Item = (u64, Factorisation);
+
+    fn next(&mut self) -> Option<(u64, Factorisation)> {
+        // Factorise another segment if we have reached the end of the current one.
+        if self.idx == self.factors.len() {
+            self.factors.clear();
+            self.factors.resize(self.factoriser.next_segment_len(), Vec::new());
+            let factors = &mut self.factors;
+            let (low, _) = self.factoriser.next_segment(|idx, p, k| factors[idx].push((p, k)))?;
+            self.low = low;
+            self.idx = 0;
+        }
+
+        self.idx += 1;
+        let factors = mem::take(&mut self.factors[self.idx - 1]);
+        Some((self.low + self.idx as u64 - 1, factors.into_iter().collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sieve::Sieve;
+
+    #[test]
+    fn test_matches_sieve() {
+        let sieve = Sieve::to_limit(1000);
+        for &(lo, hi) in &[(0, 600000), (1000000000000, 1000000100000)] {
+            let mut expected = lo.max(1);
+            for (n, factors) in Factorisations::new(lo, hi) {
+                assert_eq!(n, expected);
+                assert_eq!(Ok(factors), sieve.factorise(n));
+                expected += 1;
+            }
+            assert_eq!(expected, hi);
+        }
+    }
+}
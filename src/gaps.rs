@@ -0,0 +1,192 @@
+//! Statistics about the gaps between consecutive primes in an interval.
+
+use iterator::{MODULUS, OFFSETS, SMALL_PRIMES};
+use segsieve::SegmentedSieve;
+
+/// A gap between two consecutive primes, given by the smaller prime and the difference between
+/// them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Gap {
+    /// The smaller of the two primes.
+    pub start: u64,
+    /// The difference between the two primes.
+    pub length: u64,
+}
+
+/// Statistics about the gaps between consecutive primes in an interval: the maximal gaps, the
+/// first occurrence of each gap length, and the number of gaps of each length.
+///
+/// Only gaps between two primes which both lie in the interval are considered. The primes are
+/// found with a segmented sieve and the statistics updated as each segment is sieved, with the
+/// last prime of each segment carried over to the next, so that only enough memory is needed for
+/// the sieving primes up to the square root of the end of the interval.
+///
+/// # Examples
+///
+/// ```
+/// use primesieve::{Gap, GapStatistics};
+///
+/// let stats = GapStatistics::in_range(0, 1000);
+///
+/// let records = stats.maximal_gaps().iter().map(|gap| (gap.start, gap.length));
+/// assert_eq!(records.collect::<Vec<_>>(),
+///            vec![(2, 1), (3, 2), (7, 4), (23, 6), (89, 8), (113, 14), (523, 18), (887, 20)]);
+///
+/// assert_eq!(stats.first_occurrence(10), Some(139));
+/// assert_eq!(stats.first_occurrence(16), None);
+/// assert_eq!(stats.count(2), 35);
+/// assert_eq!(stats.num_gaps(), 167);
+/// assert_eq!(stats.largest_gap(), Some(Gap { start: 887, length: 20 }));
+/// ```
+pub struct GapStatistics {
+    /// The gaps which are longer than every gap before them in the interval.
+    maximal: Vec<Gap>,
+    /// The start of the first gap of each length, indexed by the length.
+    first: Vec<Option<u64>>,
+    /// The number of gaps of each length, indexed by the length.
+    counts: Vec<u64>,
+    /// The largest prime seen so far.
+    last: Option<u64>,
+}
+
+impl GapStatistics {
+    /// Finds the statistics for the gaps between consecutive primes in the interval `[lo, hi)`.
+    pub fn in_range(lo: u64, hi: u64) -> GapStatistics {
+        let mut stats = GapStatistics {
+            maximal: Vec::new(),
+            first: Vec::new(),
+            counts: Vec::new(),
+            last: None,
+        };
+        if hi <= lo {
+            return stats;
+        }
+
+        for &p in SMALL_PRIMES.iter().filter(|&&p| lo <= p && p < hi) {
+            stats.push(p);
+        }
+
+        let mut sieve = SegmentedSieve::new(lo, hi);
+        while sieve.advance() {
+            let mut base = sieve.segment_low();
+            for &word in sieve.segment() {
+                let mut bits = word;
+                while bits != 0 {
+                    let p = base + OFFSETS[bits.trailing_zeros() as usize];
+                    if p >= hi {
+                        return stats;
+                    }
+                    stats.push(p);
+                    bits &= bits - 1;
+                }
+                base += MODULUS;
+            }
+        }
+
+        stats
+    }
+
+    /// Updates the statistics with the next prime in the interval.
+    #[inline]
+    fn push(&mut self, p: u64) {
+        if let Some(last) = self.last {
+            let length = p - last;
+            let idx = length as usize;
+            if idx >= self.counts.len() {
+                self.counts.resize(idx + 1, 0);
+                self.first.resize(idx + 1, None);
+                self.maximal.push(Gap { start: last, length });
+            }
+            self.counts[idx] += 1;
+            if self.first[idx].is_none() {
+                self.first[idx] = Some(last);
+            }
+        }
+        self.last = Some(p);
+    }
+
+    /// Returns the maximal gaps in the interval, that is those gaps which are longer than every
+    /// gap before them, in increasing order.
+    pub fn maximal_gaps(&self) -> &[Gap] {
+        &self.maximal
+    }
+
+    /// Returns the longest gap in the interval, taking the first if there are several, or `None`
+    /// if there are fewer than two primes in the interval.
+    pub fn largest_gap(&self) -> Option<Gap> {
+        self.maximal.last().cloned()
+    }
+
+    /// Returns the start of the first gap of the given length, or `None` if there are no gaps of
+    /// this length in the interval.
+    pub fn first_occurrence(&self, length: u64) -> Option<u64> {
+        self.first.get(length as usize).cloned().unwrap_or(None)
+    }
+
+    /// Returns an iterator over the first gap of each length which occurs in the interval, in
+    /// increasing order of length.
+    pub fn first_occurrences(&self) -> impl Iterator<Item = Gap> + '_ {
+        self.first.iter().enumerate().filter_map(|(length, &start)| {
+            start.map(|start| Gap { start, length: length as u64 })
+        })
+    }
+
+    /// Returns the number of gaps of the given length in the interval.
+    pub fn count(&self, length: u64) -> u64 {
+        self.counts.get(length as usize).cloned().unwrap_or(0)
+    }
+
+    /// Returns a histogram of the gap lengths in the interval, as an iterator over the pairs
+    /// (length, count) for each length which occurs, in increasing order of length.
+    pub fn histogram(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.counts.iter().enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(length, &count)| (length as u64, count))
+    }
+
+    /// Returns the total number of gaps in the interval, which is one less than the number of
+    /// primes in the interval, if there are any.
+    pub fn num_gaps(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sieve::Sieve;
+
+    #[test]
+    fn test_matches_sieve() {
+        let limit = 20000000;
+        let sieve = Sieve::to_limit(limit);
+        let primes = sieve.iter().take_while(|&p| p < limit).collect::<Vec<u64>>();
+        for &(lo, hi) in &[(0, 0), (0, 3), (2, 4), (3, 6), (1000, 1000), (7, 1000000),
+                           (7864000, 7865000), (12345, limit)] {
+            let window = primes.iter().cloned().filter(|&p| lo <= p && p < hi).collect::<Vec<_>>();
+            let gaps = window.windows(2).map(|w| Gap { start: w[0], length: w[1] - w[0] });
+
+            let (mut maximal, mut first, mut counts) = (Vec::new(), Vec::new(), Vec::new());
+            for gap in gaps {
+                if maximal.last().is_none_or(|max: &Gap| gap.length > max.length) {
+                    maximal.push(gap);
+                }
+                if !first.iter().any(|other: &Gap| other.length == gap.length) {
+                    first.push(gap);
+                }
+                match counts.iter_mut().find(|entry: &&mut (u64, u64)| entry.0 == gap.length) {
+                    Some(entry) => entry.1 += 1,
+                    None => counts.push((gap.length, 1)),
+                }
+            }
+            first.sort_by_key(|gap| gap.length);
+            counts.sort();
+
+            let stats = GapStatistics::in_range(lo, hi);
+            assert_eq!(stats.maximal_gaps(), &maximal[..]);
+            assert_eq!(stats.first_occurrences().collect::<Vec<_>>(), first);
+            assert_eq!(stats.histogram().collect::<Vec<_>>(), counts);
+            assert_eq!(stats.num_gaps(), window.len().saturating_sub(1) as u64);
+        }
+    }
+}
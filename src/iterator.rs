@@ -1,7 +1,11 @@
 //! Iteration over the numbers encoded in a sieve.
 
-const MODULUS: u64 = 240;
-const OFFSETS: &'static [u64; 64] =
+/// The primes 2, 3 and 5, which are not encoded in a sieve.
+pub const SMALL_PRIMES: &[u64; 3] = &[2, 3, 5];
+
+pub const MODULUS: u64 = 240;
+#[allow(clippy::redundant_static_lifetimes)]
+pub const OFFSETS: &'static [u64; 64] =
     &[1, 7, 11, 13, 17, 19, 23, 29,
       31, 37, 41, 43, 47, 49, 53, 59,
       61, 67, 71, 73, 77, 79, 83, 89,
@@ -27,21 +31,19 @@ pub struct SieveIterator<'a> {
 impl<'a> SieveIterator<'a> {
     /// Create a new `SieveIterator` which is ready to iterate over the numbers encoded in the
     /// given sieve of `u64`s.
-    pub fn new(sieve: &'a [u64]) -> SieveIterator {
-        if sieve.len() == 0 {
-            SieveIterator {
-                current: 0,
-                base: 0,
-                curr_idx: 0,
-                sieve: sieve,
-            }
-        } else {
-            SieveIterator {
-                current: sieve[0],
-                base: 0,
-                curr_idx: 0,
-                sieve: sieve,
-            }
+    pub fn new(sieve: &'a [u64]) -> SieveIterator<'a> {
+        SieveIterator::with_offset(sieve, 0)
+    }
+
+    /// Create a new `SieveIterator` over the numbers encoded in the given sieve of `u64`s, where
+    /// the sieve begins at the given offset rather than at zero. The offset must be a multiple of
+    /// 240.
+    pub fn with_offset(sieve: &'a [u64], offset: u64) -> SieveIterator<'a> {
+        SieveIterator {
+            current: if sieve.is_empty() { 0 } else { sieve[0] },
+            base: offset,
+            curr_idx: 0,
+            sieve,
         }
     }
 }
@@ -92,6 +94,14 @@ mod tests {
                    vec![7, 17, 23, 29, 37, 67, 71, 77, 89, 91, 103, 113]);
     }
 
+    #[test]
+    fn test_offset() {
+        let sieve = [0b1001001100101100000001011010010];
+        let iter = SieveIterator::with_offset(&sieve, 480);
+        assert_eq!(iter.collect::<Vec<u64>>(),
+                   vec![487, 497, 503, 509, 517, 547, 551, 557, 569, 571, 583, 593]);
+    }
+
     #[test]
     fn test_medium() {
         let sieve = [0b1001001100101100000001011010010, 0b0, 0b1100101100000001011010010];
@@ -1,9 +1,53 @@
 //! A library for generating prime numbers using a segmented sieve.
 
+pub mod analytic;
+mod arith;
+mod bounds;
+mod chebyshev;
+mod count;
+mod divisors;
+mod error;
+mod factor;
+mod factorisation;
+mod factorisations;
+mod gaps;
 mod iterator;
+mod lmo;
+pub mod multiplicative;
+mod nth;
+mod presieve;
+pub mod primality;
+mod primes;
+mod primesums;
+mod progressions;
+mod rangefuncs;
 mod segsieve;
 mod segment;
 mod sieve;
+mod spf;
+pub mod tuplets;
 mod wheel;
 
-pub use sieve::{Sieve, SieveIterator};
\ No newline at end of file
+pub use bounds::{
+    nth_prime_lower_bound, nth_prime_upper_bound, prime_pi_lower_bound, prime_pi_upper_bound,
+};
+pub use chebyshev::{chebyshev_psi, chebyshev_theta};
+pub use count::count_primes;
+pub use divisors::DivisorIterator;
+pub use error::Error;
+pub use factorisation::Factorisation;
+pub use factorisations::Factorisations;
+pub use gaps::{Gap, GapStatistics};
+pub use lmo::prime_pi;
+pub use nth::nth_prime;
+pub use primes::Primes;
+pub use primesums::{prime_power_sum, prime_sum};
+pub use progressions::{
+    count_primes_by_residue, count_primes_in_progression, PrimesInProgression,
+};
+pub use rangefuncs::{
+    big_omega_range, divisor_sum_range, euler_phi_range, mobius_range, number_of_divisors_range,
+    omega_range,
+};
+pub use sieve::{GrowingSieve, Sieve, SieveIterator};
+pub use spf::SpfSieve;
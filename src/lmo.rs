@@ -0,0 +1,459 @@
+//! The Lagarias-Miller-Odlyzko algorithm for computing the prime counting function.
+//!
+//! # Overview
+//!
+//! Let `y` be a number between the cube root and the square root of `x`, and let `a` be the
+//! number of primes up to `y`. Then the number of primes up to `x` is
+//!
+//! `pi(x) = phi(x, a) + a - 1 - P2(x, a)`
+//!
+//! where `phi(x, a)` counts the numbers up to `x` with no prime factor among the first `a`
+//! primes, and `P2(x, a)` counts the numbers up to `x` which are a product of exactly two primes
+//! larger than `y`.
+//!
+//! # Details
+//!
+//! `P2(x, a)` is the sum of `pi(x / p) - pi(p) + 1` over the primes `y < p <= sqrt(x)`, and these
+//! values of `pi` are found by running a segmented sieve up to `x / y`.
+//!
+//! `phi(x, a)` is split into a sum over the ordinary leaves, which is a simple sum over the
+//! squarefree numbers up to `y`, and a sum over the special leaves, each of which needs a value of
+//! `phi(x / n, b)` for some `x / n < x / y`. These are found by sieving the numbers up to `x / y`
+//! segment by segment, crossing off the multiples of one prime at a time, and using a binary
+//! indexed tree over the `u64`s of each segment to count the numbers which remain after each
+//! prime has been crossed off.
+//!
+//! The segments use the same mod 30 encoding as the segmented sieve, so 2, 3 and 5 are never
+//! represented, and each segment starts out pre-sieved for the primes 7 to 19, after which the
+//! multiples of each larger prime are crossed off with a `Wheel30`. The first few primes are
+//! therefore never crossed off at all, and `phi(x, c)` for these primes is instead read from a
+//! small periodic table.
+//!
+//! The special leaves whose values depend only on a value of `pi` up to `y` are counted separately
+//! from the sieve, in clusters of leaves which share the same value.
+
+use std::cmp::{max, min};
+
+use arith::{icbrt, isqrt};
+use count::count_primes;
+use iterator::MODULUS;
+use presieve::{presieve, PRESIEVE_PRIMES};
+use primes::Primes;
+use segment::{count_below, get, set_off};
+use segsieve::{SegmentedSieve, SEGMENT_LEN};
+use sieve::Sieve;
+use wheel::Wheel30;
+
+/// Below this, it is quicker to simply count the primes with a sieve.
+const SIEVE_THRESHOLD: u64 = 1 << 20;
+
+/// The number of primes considered at a time when iterating backwards over the primes for `P2`.
+const CHUNK_SIZE: u64 = 1 << 22;
+
+/// Returns tables of the least prime factor and of the Möbius function for the numbers up to
+/// `limit`. The least prime factor of 1 is taken to be larger than any prime.
+fn lpf_and_mu(limit: usize) -> (Vec<u32>, Vec<i8>) {
+    let mut lpf = vec![0u32; limit + 1];
+    let mut mu = vec![1i8; limit + 1];
+    let mut primes = Vec::new();
+    if limit >= 1 {
+        lpf[1] = u32::MAX;
+    }
+
+    // A linear sieve, which finds each composite exactly once as a multiple of its least prime
+    // factor.
+    for n in 2..=limit {
+        if lpf[n] == 0 {
+            lpf[n] = n as u32;
+            mu[n] = -1;
+            primes.push(n);
+        }
+        for &p in &primes {
+            if p > lpf[n] as usize || n * p > limit {
+                break;
+            }
+            lpf[n * p] = p as u32;
+            mu[n * p] = if p == lpf[n] as usize { 0 } else { -mu[n] };
+        }
+    }
+
+    (lpf, mu)
+}
+
+/// A binary indexed tree over the `u64`s of a segment, which counts the numbers in the segment
+/// which have not yet been crossed off.
+struct CountTree {
+    tree: Vec<u32>,
+}
+
+impl CountTree {
+    /// Create a tree for a segment in which the numbers yet to be crossed off are those whose
+    /// bits are on.
+    fn new(segment: &[u64]) -> CountTree {
+        let len = segment.len();
+        let mut tree = segment.iter().map(|word| word.count_ones()).collect::<Vec<u32>>();
+        for ix in 1..=len {
+            let parent = ix + (ix & ix.wrapping_neg());
+            if parent <= len {
+                tree[parent - 1] += tree[ix - 1];
+            }
+        }
+        CountTree { tree }
+    }
+
+    /// Record that a number in the `u64` at the given index has been crossed off.
+    fn remove(&mut self, word_idx: usize) {
+        let mut ix = word_idx + 1;
+        while ix <= self.tree.len() {
+            self.tree[ix - 1] -= 1;
+            ix += ix & ix.wrapping_neg();
+        }
+    }
+
+    /// Count the numbers at indices strictly below `idx` in the given segment which have not been
+    /// crossed off.
+    fn count(&self, segment: &[u64], idx: u64) -> u64 {
+        let word_idx = (idx / MODULUS) as usize;
+        let mut count = count_below(&segment[word_idx..], idx % MODULUS);
+        let mut ix = word_idx;
+        while ix > 0 {
+            count += self.tree[ix - 1] as u64;
+            ix &= ix - 1;
+        }
+        count
+    }
+
+    /// Count all the numbers in the segment which have not been crossed off.
+    fn total(&self) -> u64 {
+        let mut count = 0;
+        let mut ix = self.tree.len();
+        while ix > 0 {
+            count += self.tree[ix - 1] as u64;
+            ix &= ix - 1;
+        }
+        count
+    }
+}
+
+/// The values of `phi(x, 6)` for `x` up to the product of the first 6 primes, from which any
+/// value of `phi(x, c)` can be calculated quickly, so that the first `c` primes never need to be
+/// crossed off when sieving.
+struct PhiTiny {
+    /// The number of numbers up to each point which are coprime to the product.
+    counts: Vec<u64>,
+}
+
+impl PhiTiny {
+    /// The number `c` of primes which are dealt with - these are 2, 3 and 5, which are left out
+    /// of the encoding of the segments, and the primes 7 to 19 which they are pre-sieved for.
+    const C: usize = 3 + 5;
+    /// The product of the first 6 primes.
+    const PRODUCT: u64 = 2 * 3 * 5 * 7 * 11 * 13;
+
+    fn new() -> PhiTiny {
+        let counts = (0..PhiTiny::PRODUCT)
+            .scan(0, |count, n| {
+                *count += [2, 3, 5, 7, 11, 13].iter().all(|&p| n % p != 0) as u64;
+                Some(*count)
+            })
+            .collect();
+        PhiTiny { counts }
+    }
+
+    /// Returns `phi(x, 6)`.
+    fn phi6(&self, x: u64) -> u64 {
+        let total = self.counts[self.counts.len() - 1];
+        (x / PhiTiny::PRODUCT) * total + self.counts[(x % PhiTiny::PRODUCT) as usize]
+    }
+
+    /// Returns `phi(x, c)`, removing the multiples of 17 and 19 from the numbers counted by
+    /// `phi(x, 6)` by inclusion-exclusion.
+    fn phi(&self, x: u64) -> u64 {
+        self.phi6(x) + self.phi6(x / (17 * 19)) - self.phi6(x / 17) - self.phi6(x / 19)
+    }
+}
+
+/// Calculates the contribution of the ordinary leaves to `phi(x, a)`, where `primes` holds the
+/// primes up to `y` indexed from 1.
+fn s1(x: u64, y: u64, primes: &[u64], lpf: &[u32], mu: &[i8], tiny: &PhiTiny) -> i128 {
+    (1..=y)
+        .filter(|&n| mu[n as usize] != 0 && lpf[n as usize] as u64 > primes[PhiTiny::C])
+        .map(|n| mu[n as usize] as i128 * tiny.phi(x / n) as i128)
+        .sum()
+}
+
+/// Returns the number `L` such that the special leaf `x / (prime * q)`, for a prime `q`, is easy
+/// whenever it is smaller than `L`.
+///
+/// A leaf `u = x / (p_b * q)` is easy if `u` is at most `y`, so that `pi(u)` can be looked up, and
+/// is smaller than `p_b^2`, so that the numbers up to `u` with no prime factor among the first
+/// `b - 1` primes are just 1 and the primes from `p_b` to `u`.
+fn easy_limit(y: u64, prime: u64) -> u64 {
+    min(y + 1, prime * prime)
+}
+
+/// Calculates the contribution to `phi(x, a)` of the easy special leaves, where `primes` holds
+/// the primes up to `y` indexed from 1, and `pi` holds the values of `pi` up to `y`.
+///
+/// The value at an easy leaf depends only on `pi(u)`, so the leaves for a given prime are dealt
+/// with in clusters which share the same value.
+fn s2_easy(x: u64, y: u64, primes: &[u64], pi: &[u32]) -> i128 {
+    let pi_y = primes.len() - 1;
+    let first = max(PhiTiny::C, pi[isqrt(y as u128) as usize] as usize) + 1;
+    let mut result = 0;
+
+    for b in first..pi_y {
+        let prime = primes[b];
+        let min_q = max(max(prime, y / prime), x / (prime * easy_limit(y, prime)));
+        if min_q >= y {
+            continue;
+        }
+
+        let mut ix = pi[min_q as usize] as usize + 1;
+        while ix <= pi_y {
+            let u = x / (prime * primes[ix]);
+            let pi_u = pi[u as usize] as usize;
+            let phi_u = 1 + pi_u.saturating_sub(b - 1);
+
+            // Find the last prime `q` for which `pi(x / (prime * q))` is the same.
+            let last_q = if pi_u == 0 { y } else { min(y, x / (prime * primes[pi_u])) };
+            let last_ix = pi[last_q as usize] as usize;
+            result += ((last_ix + 1 - ix) * phi_u) as i128;
+            ix = last_ix + 1;
+        }
+    }
+
+    result
+}
+
+/// Calculates the contribution of the remaining special leaves to `phi(x, a)`, where `primes`
+/// holds the primes up to `y` indexed from 1, and `pi` holds the values of `pi` up to `y`.
+fn s2(x: u64, y: u64, primes: &[u64], pi: &[u32], lpf: &[u32], mu: &[i8]) -> i128 {
+    let limit = x / y + 1;
+    let pi_y = primes.len() - 1;
+    let sqrt_y = isqrt(y as u128) as u64;
+    let segment_len = min(isqrt(limit as u128) as usize / MODULUS as usize + 1, SEGMENT_LEN);
+
+    // The next multiple of each prime to be crossed off, along with a wheel which generates the
+    // differences between successive multiples which are not divisible by 2, 3 or 5.
+    let mut next = primes.iter().map(|&p| (p, Wheel30::new(p, 1))).collect::<Vec<_>>();
+    let mut segment = vec![0; segment_len];
+    let mut phi = vec![0; primes.len()];
+    let mut result = 0;
+
+    let mut low = 0;
+    while low < limit {
+        let high = low + MODULUS * segment_len as u64;
+        presieve(&mut segment, low);
+        if low == 0 {
+            for &p in PRESIEVE_PRIMES {
+                set_off(&mut segment, p);
+            }
+        }
+        let mut tree = CountTree::new(&segment);
+
+        for b in PhiTiny::C + 1..pi_y {
+            let prime = primes[b];
+
+            // Find the special leaves `x / (prime * m)` which lie in this segment. Once there are
+            // none, there are none for any larger prime or any later segment either.
+            let min_m = max(x / prime / high, y / prime);
+            let max_m = min((x / prime).checked_div(low).unwrap_or(y), y);
+            if prime >= max_m {
+                break;
+            }
+
+            if prime <= sqrt_y {
+                for m in (min_m + 1..=max_m).rev() {
+                    if mu[m as usize] != 0 && prime < lpf[m as usize] as u64 {
+                        let xn = x / (prime * m);
+                        let phi_xn = phi[b] + tree.count(&segment, xn + 1 - low);
+                        result -= mu[m as usize] as i128 * phi_xn as i128;
+                    }
+                }
+            } else {
+                // Any `m <= y` with no prime factor up to `prime` is itself a prime, since the
+                // square of `prime` is larger than `y`.
+                // Leaves which are easy are dealt with elsewhere.
+                let max_hard = min(max_m, x / (prime * easy_limit(y, prime)));
+                let start = pi[min(max(min_m, prime), y) as usize] as usize + 1;
+                let end = max(start, pi[max(max_hard, prime) as usize] as usize + 1);
+                for &q in primes[start..end].iter().rev() {
+                    let xn = x / (prime * q);
+                    result += (phi[b] + tree.count(&segment, xn + 1 - low)) as i128;
+                }
+            }
+
+            // Keep a running count of the numbers below the next segment which are not divisible
+            // by any of the first `b - 1` primes, then cross off the multiples of this prime.
+            phi[b] += tree.total();
+            let (ref mut multiple, ref mut wheel) = next[b];
+            while *multiple < high {
+                let idx = *multiple - low;
+                if get(&segment, idx) {
+                    set_off(&mut segment, idx);
+                    tree.remove((idx / MODULUS) as usize);
+                }
+                *multiple += wheel.next_diff();
+            }
+        }
+
+        low = high;
+    }
+
+    result
+}
+
+/// A running count of the primes up to a number which only ever increases.
+struct PrimeCounter {
+    /// The sieve which finds the primes up to the final limit.
+    sieve: SegmentedSieve,
+    /// The index of a `u64` in the current segment.
+    word_idx: usize,
+    /// The number of primes before the `u64` at `word_idx`.
+    count: u64,
+}
+
+impl PrimeCounter {
+    fn new(limit: u64) -> PrimeCounter {
+        let mut sieve = SegmentedSieve::new(0, limit + 1);
+        sieve.advance();
+        PrimeCounter {
+            sieve,
+            word_idx: 0,
+            count: 3,
+        }
+    }
+
+    /// Returns the number of primes up to `n`, which must not be smaller than any previous `n`.
+    fn pi(&mut self, n: u64) -> u64 {
+        // Move on to the segment containing `n`.
+        while n >= self.sieve.segment_low() + MODULUS * self.sieve.segment().len() as u64 {
+            self.count += count_below(&self.sieve.segment()[self.word_idx..], u64::MAX);
+            self.word_idx = 0;
+            self.sieve.advance();
+        }
+
+        // Then move on to the `u64` containing `n`.
+        let idx = n + 1 - self.sieve.segment_low();
+        let segment = self.sieve.segment();
+        while MODULUS * (self.word_idx as u64 + 1) < idx {
+            self.count += segment[self.word_idx].count_ones() as u64;
+            self.word_idx += 1;
+        }
+
+        let word = &segment[self.word_idx..self.word_idx + 1];
+        self.count + count_below(word, idx - MODULUS * self.word_idx as u64)
+    }
+}
+
+/// Calculates `P2(x, a)`, where `a` is the number of primes up to `y`.
+fn p2(x: u64, y: u64, pi_y: u64) -> i128 {
+    let sqrt = isqrt(x as u128) as u64;
+    if sqrt <= y {
+        return 0;
+    }
+
+    // Go backwards over the primes `y < p <= sqrt(x)` a chunk at a time, so that the values
+    // `x / p` increase and can be counted using a single forward sieve.
+    let mut counter = PrimeCounter::new(x / (y + 1));
+    let mut pi_p = pi_y + count_primes(y + 1, sqrt + 1);
+    let mut result = 0;
+    let mut high = sqrt + 1;
+    while high > y + 1 {
+        let low = max(y + 1, high.saturating_sub(CHUNK_SIZE));
+        let chunk = Sieve::in_range(low, high);
+        let primes = chunk.iter().take_while(|&p| p < high).collect::<Vec<u64>>();
+        for &p in primes.iter().rev() {
+            result += counter.pi(x / p) as i128 - pi_p as i128 + 1;
+            pi_p -= 1;
+        }
+        high = low;
+    }
+
+    result
+}
+
+/// Returns the number of primes not greater than `x`.
+///
+/// This uses the combinatorial algorithm of Lagarias, Miller and Odlyzko, which takes time
+/// roughly proportional to `x^(2/3)` and memory roughly proportional to `x^(1/3)`, and so can
+/// count primes far beyond the point where sieving every number up to `x` is feasible.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(primesieve::prime_pi(0), 0);
+/// assert_eq!(primesieve::prime_pi(100), 25);
+/// assert_eq!(primesieve::prime_pi(10000000000), 455052511);
+/// ```
+pub fn prime_pi(x: u64) -> u64 {
+    if x < SIEVE_THRESHOLD {
+        return count_primes(0, x + 1);
+    }
+
+    // Choose `y` a little larger than the cube root of `x`, which balances the time taken for the
+    // special leaves against the time taken for everything else.
+    let alpha = max(1, ((x as f64).ln() / 4.0) as u64);
+    let y = min(icbrt(x) * alpha, isqrt(x as u128) as u64);
+
+    let mut primes = vec![0];
+    primes.extend(Primes::new().take_while(|&p| p <= y));
+    let pi_y = (primes.len() - 1) as u64;
+    let (lpf, mu) = lpf_and_mu(y as usize);
+
+    // Tabulate the values of `pi` up to `y`.
+    let mut pi = vec![0; y as usize + 1];
+    for &p in &primes[1..] {
+        pi[p as usize] = 1;
+    }
+    for n in 1..pi.len() {
+        pi[n] += pi[n - 1];
+    }
+
+    let tiny = PhiTiny::new();
+    let s1 = s1(x, y, &primes, &lpf, &mu, &tiny);
+    let s2 = s2(x, y, &primes, &pi, &lpf, &mu) + s2_easy(x, y, &primes, &pi);
+    (s1 + s2 + pi_y as i128 - 1 - p2(x, y, pi_y)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lpf_and_mu() {
+        let (lpf, mu) = lpf_and_mu(30);
+        assert_eq!(lpf[2..].to_vec(), vec![2, 3, 2, 5, 2, 7, 2, 3, 2, 11, 2, 13, 2, 3, 2, 17, 2,
+                                           19, 2, 3, 2, 23, 2, 5, 2, 3, 2, 29, 2]);
+        assert_eq!(mu[1..].to_vec(), vec![1, -1, -1, 0, -1, 1, -1, 0, 0, 1, -1, 0, -1, 1, 1, 0,
+                                          -1, 0, -1, 0, 1, 1, -1, 0, 0, 1, 0, 0, -1, -1]);
+    }
+
+    #[test]
+    fn test_small_values() {
+        for &x in &[SIEVE_THRESHOLD, SIEVE_THRESHOLD + 1, 3000000, 12345678, 87654321] {
+            assert_eq!(prime_pi(x), count_primes(0, x + 1));
+        }
+    }
+
+    #[test]
+    fn test_powers_of_ten() {
+        let expected = [4, 25, 168, 1229, 9592, 78498, 664579, 5761455, 50847534, 455052511,
+                        4118054813];
+        let mut x = 1;
+        for &pi in &expected {
+            x *= 10;
+            assert_eq!(prime_pi(x), pi);
+        }
+    }
+
+    /// Run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_large_powers_of_ten() {
+        assert_eq!(prime_pi(10000000000000), 346065536839);
+        assert_eq!(prime_pi(100000000000000), 3204941750802);
+        assert_eq!(prime_pi(1000000000000000), 29844570422669);
+    }
+}
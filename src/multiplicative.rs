@@ -0,0 +1,177 @@
+//! Multiplicative arithmetic functions, which are determined by their values at prime powers.
+//!
+//! A function `f` is multiplicative if `f(1) = 1` and `f(mn) = f(m) f(n)` whenever `m` and `n`
+//! are coprime. Implementing the `Multiplicative` trait for a type describes such a function, which
+//! can then be evaluated at a single number with `Sieve::evaluate`, or at every number up to a
+//! limit with `Sieve::tabulate`.
+//!
+//! # Examples
+//!
+//! ```
+//! use primesieve::Sieve;
+//! use primesieve::multiplicative::{EulerPhi, Multiplicative, Sigma};
+//!
+//! /// The number of square divisors of `n`.
+//! struct SquareDivisors;
+//!
+//! impl Multiplicative for SquareDivisors {
+//!     type Value = u64;
+//!
+//!     fn prime_power(_p: u64, k: u64) -> u64 {
+//!         k / 2 + 1
+//!     }
+//! }
+//!
+//! let sieve = Sieve::to_limit(100);
+//! assert_eq!(sieve.evaluate::<SquareDivisors>(2 * 2 * 2 * 3 * 3), Ok(4));
+//! assert_eq!(sieve.evaluate::<EulerPhi>(36), Ok(12));
+//! assert_eq!(sieve.evaluate::<Sigma<1>>(36), Ok(91));
+//!
+//! assert_eq!(Sieve::tabulate::<SquareDivisors>(9), vec![0, 1, 1, 1, 2, 1, 1, 1, 2, 2]);
+//! ```
+
+use std::ops::Mul;
+
+use arith::divisor_power_sum;
+
+/// A multiplicative arithmetic function, described by its values at the prime powers.
+pub trait Multiplicative {
+    /// The type of the values taken by the function.
+    type Value: Copy + Mul<Output = Self::Value> + From<u8>;
+
+    /// The value of the function at `p^k`, where `p` is prime and `k > 0`.
+    fn prime_power(p: u64, k: u64) -> Self::Value;
+}
+
+/// Euler's totient function `ϕ(n)`, the number of integers in `[1, n]` coprime to `n`.
+pub struct EulerPhi;
+
+impl Multiplicative for EulerPhi {
+    type Value = u64;
+
+    fn prime_power(p: u64, k: u64) -> u64 {
+        (p - 1) * p.pow(k as u32 - 1)
+    }
+}
+
+/// The Möbius function `μ(n)`, which is `(-1)^r` if `n` is a product of `r` distinct primes, and 0
+/// if `n` is not squarefree.
+pub struct Mobius;
+
+impl Multiplicative for Mobius {
+    type Value = i64;
+
+    fn prime_power(_p: u64, k: u64) -> i64 {
+        if k == 1 { -1 } else { 0 }
+    }
+}
+
+/// The divisor function `σ_K(n)`, the sum of the `K`th powers of the divisors of `n`. In
+/// particular, `Sigma<0>` counts the divisors of `n`.
+pub struct Sigma<const K: u32>;
+
+impl<const K: u32> Multiplicative for Sigma<K> {
+    type Value = u128;
+
+    fn prime_power(p: u64, k: u64) -> u128 {
+        divisor_power_sum(p, K, k)
+    }
+}
+
+/// Liouville's function `λ(n)`, which is `(-1)^r` where `r` is the number of prime factors of `n`
+/// counted with multiplicity.
+pub struct Liouville;
+
+impl Multiplicative for Liouville {
+    type Value = i64;
+
+    fn prime_power(_p: u64, k: u64) -> i64 {
+        if k % 2 == 0 { 1 } else { -1 }
+    }
+}
+
+/// Jordan's totient function `J_K(n)`, the number of `K`-tuples of integers in `[1, n]` which
+/// together with `n` have no common factor. In particular, `Jordan<1>` is Euler's totient
+/// function.
+pub struct Jordan<const K: u32>;
+
+impl<const K: u32> Multiplicative for Jordan<K> {
+    type Value = u128;
+
+    fn prime_power(p: u64, k: u64) -> u128 {
+        let lower = (p as u128).pow((k as u32 - 1) * K);
+        lower * (p as u128).pow(K) - lower
+    }
+}
+
+/// The radical `rad(n)`, the product of the distinct primes dividing `n`.
+pub struct Radical;
+
+impl Multiplicative for Radical {
+    type Value = u64;
+
+    fn prime_power(p: u64, _k: u64) -> u64 {
+        p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arith::gcd;
+    use sieve::Sieve;
+
+    /// Checks that tabulating `F` agrees with evaluating it at each number, and with the given
+    /// direct calculation.
+    fn check<F: Multiplicative, G: Fn(u64) -> F::Value>(direct: G)
+    where
+        F::Value: PartialEq + ::std::fmt::Debug,
+    {
+        let limit = 1000;
+        let sieve = Sieve::to_limit(100);
+        let table = Sieve::tabulate::<F>(limit);
+        assert_eq!(table.len(), limit as usize + 1);
+        for n in 1..=limit {
+            assert_eq!(table[n as usize], sieve.evaluate::<F>(n).unwrap());
+            assert_eq!(table[n as usize], direct(n));
+        }
+    }
+
+    /// Returns the prime factors of `n`, with multiplicity.
+    fn prime_factors(mut n: u64) -> Vec<u64> {
+        let mut factors = Vec::new();
+        let mut p = 2;
+        while n > 1 {
+            while n % p == 0 {
+                n /= p;
+                factors.push(p);
+            }
+            p += 1;
+        }
+        factors
+    }
+
+    #[test]
+    fn test_builtins() {
+        let divisors = |n: u64| (1..=n).filter(move |&d| n % d == 0);
+        let mobius = |n: u64| {
+            let factors = prime_factors(n);
+            let squarefree = factors.windows(2).all(|w| w[0] != w[1]);
+            if squarefree { (-1i64).pow(factors.len() as u32) } else { 0 }
+        };
+
+        check::<EulerPhi, _>(|n| (1..=n).filter(|&m| gcd(m, n) == 1).count() as u64);
+        check::<Mobius, _>(mobius);
+        check::<Sigma<0>, _>(|n| divisors(n).count() as u128);
+        check::<Sigma<2>, _>(|n| divisors(n).map(|d| (d * d) as u128).sum());
+        check::<Liouville, _>(|n| (-1i64).pow(prime_factors(n).len() as u32));
+        check::<Jordan<2>, _>(|n| {
+            divisors(n).map(|d| mobius(n / d) * (d * d) as i64).sum::<i64>() as u128
+        });
+        check::<Radical, _>(|n| {
+            let mut factors = prime_factors(n);
+            factors.dedup();
+            factors.iter().product()
+        });
+    }
+}
@@ -0,0 +1,114 @@
+//! Finding the `n`th prime without sieving all the way up to it.
+
+use std::cmp::max;
+
+use analytic::riemann_r_inverse;
+use lmo::prime_pi;
+use primes::Primes;
+use sieve::Sieve;
+
+/// Below this, it is quicker to simply iterate over the primes.
+const ITERATE_THRESHOLD: u64 = 100000;
+
+/// The smallest interval which is sieved at a time when searching near the estimate.
+const MIN_WINDOW: u64 = 1 << 16;
+
+/// The index of the largest prime which fits in a `u64`, which is `π(2^64) - 1`.
+const MAX_INDEX: u64 = 425656284035217742;
+
+/// Returns the `n`th prime number, indexed from 0.
+///
+/// This first estimates the `n`th prime using the inverse of Riemann's `R` function, then counts
+/// the primes up to the estimate using `prime_pi`, and finally sieves the short interval between
+/// the estimate and the true answer. This is far quicker than sieving every number up to the
+/// answer.
+///
+/// # Panics
+///
+/// Panics if `n` is larger than `π(2^64) - 1 = 425656284035217742`, since the `n`th prime does
+/// not fit in a `u64`.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(primesieve::nth_prime(0), 2);
+/// assert_eq!(primesieve::nth_prime(99), 541);
+/// assert_eq!(primesieve::nth_prime(9999999), 179424673);
+/// ```
+pub fn nth_prime(n: u64) -> u64 {
+    assert!(n <= MAX_INDEX, "the prime with index {} does not fit in a u64", n);
+    if n < ITERATE_THRESHOLD {
+        return Primes::new().nth(n as usize).unwrap();
+    }
+
+    // We are looking for the `k`th prime, indexed from 1, and know that there are `count` primes
+    // up to the estimate.
+    let k = n + 1;
+    let estimate = riemann_r_inverse(k as f64) as u64;
+    let count = prime_pi(estimate);
+
+    // Sieve intervals whose length should be enough to cover the distance to the answer, based
+    // on the average gap between primes near the estimate.
+    let gap = (estimate as f64).ln();
+    let window = max(MIN_WINDOW, (gap * count.abs_diff(k) as f64 * 1.1) as u64);
+
+    if count < k {
+        // Go forwards from the estimate until we have seen enough primes.
+        let mut needed = k - count;
+        let mut low = estimate.saturating_add(1);
+        loop {
+            let high = low.saturating_add(window);
+            let sieve = Sieve::in_range(low, high);
+            let found = sieve.iter().take_while(|&p| p < high).count() as u64;
+            if found >= needed {
+                return sieve.nth_prime((needed - 1) as usize).unwrap();
+            }
+            needed -= found;
+            low += window;
+        }
+    } else {
+        // Go backwards from the estimate, looking for the `excess`th largest prime.
+        let mut excess = count - k + 1;
+        let mut high = estimate.saturating_add(1);
+        loop {
+            let low = high.saturating_sub(window);
+            let sieve = Sieve::in_range(low, high);
+            let found = sieve.iter().take_while(|&p| p < high).count() as u64;
+            if found >= excess {
+                return sieve.nth_prime((found - excess) as usize).unwrap();
+            }
+            excess -= found;
+            high = low;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "does not fit in a u64")]
+    fn test_too_large() {
+        nth_prime(MAX_INDEX + 1);
+    }
+
+    #[test]
+    fn test_small_values() {
+        let sieve = Sieve::to_n_primes(200000);
+        for n in (ITERATE_THRESHOLD - 10..ITERATE_THRESHOLD + 10).chain(150000..150010) {
+            assert_eq!(Some(nth_prime(n)), sieve.nth_prime(n as usize));
+        }
+    }
+
+    #[test]
+    fn test_powers_of_ten() {
+        let expected = [29, 541, 7919, 104729, 1299709, 15485863, 179424673, 2038074743,
+                        22801763489];
+        let mut n = 1;
+        for &p in &expected {
+            n *= 10;
+            assert_eq!(nth_prime(n - 1), p);
+        }
+    }
+}
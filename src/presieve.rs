@@ -0,0 +1,91 @@
+//! Pre-sieving of segments using a precomputed pattern for the primes 7, 11, 13, 17 and 19.
+//!
+//! In the encoding used by the module `segment`, each byte represents a block of 30 numbers, so
+//! the pattern of multiples of 7, 11, 13, 17 and 19 repeats every 7 * 11 * 13 * 17 * 19 bytes. A
+//! fresh segment can therefore start from a copy of the right part of this pattern, instead of
+//! having the multiples of these primes crossed off one at a time.
+
+use std::sync::OnceLock;
+
+use segsieve::SEGMENT_LEN;
+
+/// The primes whose multiples are removed by pre-sieving.
+pub const PRESIEVE_PRIMES: &[u64] = &[7, 11, 13, 17, 19];
+
+/// The length, in bytes, of the repeating pattern.
+const PERIOD: usize = 7 * 11 * 13 * 17 * 19;
+
+/// The bit within a byte which represents each residue modulo 30, if any.
+const BITS: &[Option<u8>; 30] =
+    &[None, Some(0), None, None, None, None, None, Some(1), None, None,
+      None, Some(2), None, Some(3), None, None, None, Some(4), None, Some(5),
+      None, None, None, Some(6), None, None, None, None, None, Some(7)];
+
+static PATTERN: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Returns the pattern, extended past a full period by enough bytes that the pattern for any
+/// segment can be read off from a single contiguous slice.
+fn pattern() -> &'static [u8] {
+    PATTERN.get_or_init(|| {
+        let mut pattern = vec![!0; PERIOD + 8 * SEGMENT_LEN];
+        let limit = 30 * pattern.len() as u64;
+        for &prime in PRESIEVE_PRIMES {
+            for multiple in (prime..limit).step_by(2 * prime as usize) {
+                if let Some(bit) = BITS[(multiple % 30) as usize] {
+                    pattern[(multiple / 30) as usize] &= !(1 << bit);
+                }
+            }
+        }
+        pattern
+    })
+}
+
+/// Fill the given segment, which begins at the number `low`, with the encoding of the numbers
+/// which are not divisible by any of `PRESIEVE_PRIMES`. Note that this includes the primes in
+/// `PRESIEVE_PRIMES` themselves.
+///
+/// The segment must be no longer than `SEGMENT_LEN` `u64`s, and `low` must be a multiple of 240.
+pub fn presieve(segment: &mut [u64], low: u64) {
+    let start = ((low / 30) % PERIOD as u64) as usize;
+    let bytes = &pattern()[start..start + 8 * segment.len()];
+    for (word, chunk) in segment.iter_mut().zip(bytes.chunks_exact(8)) {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(chunk);
+        *word = u64::from_le_bytes(buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use segment::set_off;
+    use wheel::Wheel30;
+
+    /// Cross off the odd multiples of the pre-sieving primes by hand, for comparison.
+    fn cross_off(segment: &mut [u64], low: u64) {
+        let high = low + 240 * segment.len() as u64;
+        for &prime in PRESIEVE_PRIMES {
+            let mut mult = low.div_ceil(prime).max(1);
+            while mult % 2 == 0 || mult % 3 == 0 || mult % 5 == 0 {
+                mult += 1;
+            }
+            let mut wheel = Wheel30::new(prime, mult);
+            let mut multiple = prime * mult;
+            while multiple < high {
+                set_off(segment, multiple - low);
+                multiple += wheel.next_diff();
+            }
+        }
+    }
+
+    #[test]
+    fn test_presieve() {
+        for &low in &[0, 240, 77597520, 1000000000080, 240 * 323323 * 5 - 2400] {
+            let mut expected = vec![!0; 1000];
+            cross_off(&mut expected, low);
+            let mut segment = vec![0; 1000];
+            presieve(&mut segment, low);
+            assert_eq!(segment, expected);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Primality tests which do not need a sieve.
+//!
+//! `miller_rabin` is a deterministic test for any `u64`, while `baillie_psw` extends to `u128`.
+//! No composite number is known to pass the Baillie-PSW test, and it is known to be correct for
+//! every number below `2^64`.
+
+use arith::{isqrt, jacobi, Montgomery128, Montgomery64};
+
+/// The bases for which the strong probable prime test is known to be correct for every 64-bit
+/// integer, due to Jim Sinclair.
+const BASES: &[u64] = &[2, 325, 9375, 28178, 450775, 9780504, 1795265022];
+
+/// Returns whether `n` is a strong probable prime to the given base, where `n` is odd and
+/// `n - 1 = d * 2^s` with `d` odd.
+fn is_strong_probable_prime(mont: &Montgomery64, d: u64, s: u32, base: u64) -> bool {
+    let one = mont.one();
+    let minus_one = mont.sub(0, one);
+    let mut x = mont.pow(mont.encode(base), d);
+    if x == one || x == minus_one {
+        return true;
+    }
+    for _ in 1..s {
+        x = mont.mul(x, x);
+        if x == minus_one {
+            return true;
+        }
+    }
+    false
+}
+
+/// Returns whether or not `n` is prime, using a deterministic Miller-Rabin test.
+///
+/// The test uses a fixed set of seven bases which is known to give the correct answer for every
+/// `u64`, so the result is never wrong.
+pub fn miller_rabin(n: u64) -> bool {
+    // Deal with small and even numbers separately.
+    if n < 4 {
+        return n >= 2;
+    }
+    if n % 2 == 0 {
+        return false;
+    }
+
+    let mont = Montgomery64::new(n);
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    BASES.iter().all(|&base| base % n == 0 || is_strong_probable_prime(&mont, d, s, base))
+}
+
+/// The small primes used to quickly rule out most composite numbers before the Baillie-PSW test.
+const TRIAL_DIVISORS: &[u128] = &[3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61];
+
+/// Returns whether `n` is a strong probable prime to base 2, for odd `n`.
+fn is_strong_base_2_probable_prime(mont: &Montgomery128) -> bool {
+    let n = mont.modulus();
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+
+    let one = mont.one();
+    let minus_one = mont.sub(0, one);
+    let mut x = mont.pow(mont.add(one, one), d);
+    if x == one || x == minus_one {
+        return true;
+    }
+    for _ in 1..s {
+        x = mont.mul(x, x);
+        if x == minus_one {
+            return true;
+        }
+    }
+    false
+}
+
+/// Returns whether `n` is a strong Lucas probable prime, using Selfridge's choice of parameters,
+/// for odd `n` which is not a perfect square.
+fn is_strong_lucas_probable_prime(mont: &Montgomery128) -> bool {
+    let n = mont.modulus();
+
+    // Find the first `D` in the sequence 5, -7, 9, -11, ... with Jacobi symbol `(D / n) = -1`.
+    // Since `n` is not a square, such a `D` exists.
+    let mut d: i64 = 5;
+    loop {
+        let residue = if d > 0 { d as u128 % n } else { n - (d.unsigned_abs() as u128 % n) };
+        match jacobi(residue, n) {
+            -1 => break,
+            0 if residue != 0 => return false,
+            _ => d = if d > 0 { -d - 2 } else { -d + 2 },
+        }
+    }
+
+    // Parameters P = 1 and Q = (1 - D) / 4, reduced modulo `n` and put into Montgomery form.
+    let to_mont = |x: i64| {
+        let residue = x.unsigned_abs() as u128 % n;
+        let residue = if x < 0 && residue != 0 { n - residue } else { residue };
+        mont.encode(residue)
+    };
+    let big_d = to_mont(d);
+    let q = to_mont((1 - d) / 4);
+
+    // Write n + 1 = k * 2^s with `k` odd, and calculate U_k, V_k and Q^k by the binary method.
+    let s = (n + 1).trailing_zeros();
+    let k = (n + 1) >> s;
+    let (mut u, mut v, mut q_k) = (mont.one(), mont.one(), q);
+    for bit in (0..127 - k.leading_zeros()).rev() {
+        // Double the index.
+        u = mont.mul(u, v);
+        v = mont.sub(mont.mul(v, v), mont.add(q_k, q_k));
+        q_k = mont.mul(q_k, q_k);
+
+        // Increment the index, if this bit is set.
+        if (k >> bit) & 1 == 1 {
+            let (old_u, old_v) = (u, v);
+            u = mont.half(mont.add(old_u, old_v));
+            v = mont.half(mont.add(mont.mul(big_d, old_u), old_v));
+            q_k = mont.mul(q_k, q);
+        }
+    }
+
+    if u == 0 || v == 0 {
+        return true;
+    }
+    for _ in 1..s {
+        v = mont.sub(mont.mul(v, v), mont.add(q_k, q_k));
+        q_k = mont.mul(q_k, q_k);
+        if v == 0 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Returns whether or not `n` is prime, using the Baillie-PSW test, which combines a strong
+/// probable prime test to base 2 with a strong Lucas probable prime test.
+///
+/// There are no known composite numbers which pass this test, and it has been verified that there
+/// are none below `2^64`.
+///
+/// # Examples
+///
+/// ```
+/// use primesieve::primality::baillie_psw;
+///
+/// assert!(baillie_psw(2));
+/// assert!(!baillie_psw(2047));
+/// assert!(baillie_psw(170141183460469231731687303715884105727));
+/// assert!(!baillie_psw(18446744073709551557 * 18446744073709551533));
+/// ```
+pub fn baillie_psw(n: u128) -> bool {
+    // Deal with small and even numbers, and those with small factors, separately.
+    if n < 4 {
+        return n >= 2;
+    }
+    if n % 2 == 0 {
+        return false;
+    }
+    for &p in TRIAL_DIVISORS {
+        if n % p == 0 {
+            return n == p;
+        }
+    }
+
+    // The Lucas test needs `n` to not be a perfect square.
+    let root = isqrt(n);
+    if root * root == n {
+        return false;
+    }
+
+    let mont = Montgomery128::new(n);
+    is_strong_base_2_probable_prime(&mont) && is_strong_lucas_probable_prime(&mont)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sieve::Sieve;
+
+    #[test]
+    fn test_small_values() {
+        let sieve = Sieve::to_limit(1000000);
+        let primes = sieve.iter().take_while(|&p| p < 1000000).collect::<Vec<u64>>();
+        let tested = (0..1000000).filter(|&n| miller_rabin(n)).collect::<Vec<u64>>();
+        assert_eq!(tested, primes);
+    }
+
+    #[test]
+    fn test_baillie_psw_small_values() {
+        let sieve = Sieve::to_limit(1000000);
+        let primes = sieve.iter().take_while(|&p| p < 1000000).collect::<Vec<u64>>();
+        let tested = (0..1000000).filter(|&n| baillie_psw(n as u128)).collect::<Vec<u64>>();
+        assert_eq!(tested, primes);
+    }
+
+    #[test]
+    fn test_baillie_psw_large_values() {
+        // Strong pseudoprimes to base 2 and strong Lucas pseudoprimes pass half of the test each.
+        for &n in &[2047, 3277, 4835703300946828047872557, 4835703305485612058950657] {
+            assert!(is_strong_base_2_probable_prime(&Montgomery128::new(n)));
+            assert!(!baillie_psw(n));
+        }
+        for &n in &[5459, 5777, 10877, 16109, 18971] {
+            assert!(is_strong_lucas_probable_prime(&Montgomery128::new(n)));
+            assert!(!baillie_psw(n));
+        }
+
+        assert!(baillie_psw(1267650600228229401496703205361));
+        assert!(baillie_psw(1267650600228229401496703205653));
+        assert!(baillie_psw(340282366920938463463374607431768211297));
+        assert!(!baillie_psw(340282366920938462614824380041128836353));
+        assert!(!baillie_psw(u128::MAX));
+        assert!(!baillie_psw(9223372036854775837 * 9223372036854775837));
+    }
+
+    #[test]
+    fn test_large_values() {
+        // Strong pseudoprimes to several small bases, and some large primes and composites.
+        assert!(!miller_rabin(3215031751));
+        assert!(!miller_rabin(341550071728321));
+        assert!(!miller_rabin(3825123056546413051));
+        assert!(!miller_rabin(18446744073709551615));
+        assert!(!miller_rabin(4294967291 * 4294967279));
+        assert!(miller_rabin(4294967291));
+        assert!(miller_rabin(1000000000000000003));
+        assert!(miller_rabin(18446744073709551557));
+    }
+}
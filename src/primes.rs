@@ -0,0 +1,91 @@
+//! An unbounded iterator over the prime numbers, which sieves for them lazily one segment at a
+//! time.
+
+use iterator::{MODULUS, OFFSETS, SMALL_PRIMES};
+use segsieve::SegmentedSieve;
+
+/// An iterator over all of the prime numbers, in increasing order.
+///
+/// The primes are found using a segmented sieve which is advanced only as far as is needed to
+/// yield the next prime, so there is no need to decide on a limit up front, and the memory used
+/// grows only with the square root of the largest prime yielded.
+///
+/// # Examples
+///
+/// ```
+/// let primes = primesieve::Primes::new();
+/// assert_eq!(primes.take_while(|&x| x < 100).collect::<Vec<u64>>(),
+///            vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41,
+///                 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97]);
+/// ```
+pub struct Primes {
+    /// The number of the small primes 2, 3 and 5 which have been yielded so far.
+    small: usize,
+    /// The sieve which finds the primes in each successive segment.
+    sieve: SegmentedSieve,
+    /// The index in the current segment of the current `u64`.
+    curr_idx: usize,
+    /// The current `u64` we are extracting primes from.
+    current: u64,
+}
+
+impl Primes {
+    /// Create a new `Primes` iterator, starting from the prime 2.
+    pub fn new() -> Primes {
+        Primes {
+            small: 0,
+            sieve: SegmentedSieve::unbounded(0),
+            curr_idx: 0,
+            current: 0,
+        }
+    }
+}
+
+impl Default for Primes {
+    fn default() -> Primes {
+        Primes::new()
+    }
+}
+
+impl Iterator for Primes {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        // Yield a small prime if needed.
+        if self.small < SMALL_PRIMES.len() {
+            self.small += 1;
+            return Some(SMALL_PRIMES[self.small - 1]);
+        }
+
+        // If all the numbers from the current `u64` have been considered, look for the next `u64`
+        // which encodes a number, sieving another segment whenever we reach the end of one.
+        while self.current == 0 {
+            self.curr_idx += 1;
+            while self.curr_idx >= self.sieve.segment().len() {
+                if !self.sieve.advance() {
+                    return None;
+                }
+                self.curr_idx = 0;
+            }
+            self.current = self.sieve.segment()[self.curr_idx];
+        }
+
+        // Get the next number from the current `u64`.
+        let bit = self.current.trailing_zeros();
+        self.current &= self.current - 1;
+        Some(self.sieve.segment_low() + MODULUS * self.curr_idx as u64 + OFFSETS[bit as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sieve::Sieve;
+
+    #[test]
+    fn test_matches_sieve() {
+        let sieve = Sieve::to_limit(20000000);
+        let primes = Primes::new().take_while(|&p| p < 20000000).collect::<Vec<u64>>();
+        assert_eq!(primes, sieve.iter().take_while(|&p| p < 20000000).collect::<Vec<u64>>());
+    }
+}
@@ -0,0 +1,233 @@
+//! Sums of the primes, and of powers of the primes, up to a given bound.
+//!
+//! # Overview
+//!
+//! Let `S(v, p)` be the sum of `f(n)` over the numbers `2 <= n <= v` which are either prime or
+//! have no prime factor smaller than `p`, where `f(n) = n^k` is completely multiplicative. Then
+//! `S(v, 2)` is easily found from a formula for the sum of the `k`th powers, and once `p` passes
+//! the square root of `v`, `S(v, p)` is the sum of `f` over the primes up to `v`. Moving past
+//! each prime `p` removes the numbers whose least prime factor is `p`, which gives
+//!
+//! `S(v, p + 1) = S(v, p) - f(p) (S(v / p, p) - S(p - 1, p))`
+//!
+//! for `v >= p^2`, with `S(v, p)` unchanged for smaller `v`. Only the values of `v` of the form
+//! `x / n` ever appear, and there are about `2 sqrt(x)` of them, so the whole calculation takes
+//! `O(x^(3/4))` time and `O(sqrt(x))` memory.
+
+use arith::{add_mod, isqrt, mul_mod, pow_mod, sub_mod};
+use iterator::{SieveIterator, SMALL_PRIMES};
+use segsieve::SegmentedSieve;
+use sieve::Sieve;
+
+/// Below this, it is quicker to simply sum the primes found with a sieve.
+const SIEVE_THRESHOLD: u64 = 1 << 20;
+
+/// Calls `f(p)` for each prime `p` up to `x`, using a segmented sieve.
+fn for_each_prime<F: FnMut(u64)>(x: u64, mut f: F) {
+    for &p in SMALL_PRIMES.iter().filter(|&&p| p <= x) {
+        f(p);
+    }
+    let mut sieve = SegmentedSieve::new(0, x + 1);
+    while sieve.advance() {
+        for p in SieveIterator::with_offset(sieve.segment(), sieve.segment_low()) {
+            if p > x {
+                return;
+            }
+            f(p);
+        }
+    }
+}
+
+/// Runs the dynamic programming described in the module documentation, and returns the value of
+/// `S(x, p)` for `p` larger than the square root of `x`.
+///
+/// The sums are held in a type `T`. The function `initial(v)` must return `S(v, 2)`, `weight(p)`
+/// must return `f(p)`, and `remove(s, w, t, u)` must return `s - w (t - u)`. The bound `x` must be
+/// positive.
+fn lucy<T, I, W, R>(x: u64, initial: I, weight: W, remove: R) -> T
+where
+    T: Copy,
+    I: Fn(u64) -> T,
+    W: Fn(u64) -> T,
+    R: Fn(T, T, T, T) -> T,
+{
+    // The values `S(v)` for `v <= r` are held in `small[v]`, and those for `v = x / i > r` in
+    // `large[i]`.
+    let r = isqrt(x as u128) as u64;
+    let mut small = (0..=r).map(&initial).collect::<Vec<T>>();
+    let mut large = (0..=r).map(|i| initial(x.checked_div(i).unwrap_or(0))).collect::<Vec<T>>();
+
+    let primes = Sieve::to_limit(r);
+    for p in primes.iter().take_while(|&p| p <= r) {
+        let below = small[p as usize - 1];
+        let square = p * p;
+        let w = weight(p);
+
+        for i in 1..=(x / square).min(r) {
+            let quotient = match i * p {
+                ip if ip <= r => large[ip as usize],
+                ip => small[(x / ip) as usize],
+            };
+            large[i as usize] = remove(large[i as usize], w, quotient, below);
+        }
+        for v in (square..=r).rev() {
+            small[v as usize] = remove(small[v as usize], w, small[(v / p) as usize], below);
+        }
+    }
+
+    large[1]
+}
+
+/// Returns the sum of the primes up to and including `x`.
+///
+/// Small values of `x` are handled by summing the primes found with a segmented sieve, and larger
+/// values using Lucy's `O(x^(3/4))` algorithm, described in the documentation for
+/// `prime_power_sum`. The result always fits in a `u128`.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(primesieve::prime_sum(10), 2 + 3 + 5 + 7);
+/// assert_eq!(primesieve::prime_sum(2000000), 142913828922);
+/// assert_eq!(primesieve::prime_sum(10000000000), 2220822432581729238);
+/// ```
+pub fn prime_sum(x: u64) -> u128 {
+    if x < SIEVE_THRESHOLD {
+        let mut sum = 0;
+        for_each_prime(x, |p| sum += p as u128);
+        return sum;
+    }
+
+    let initial = |v: u64| ((v as u128 * (v as u128 + 1)) / 2).saturating_sub(1);
+    lucy(x, initial, |p| p as u128, |s, w, t, u| s - w * (t - u))
+}
+
+/// Returns the sum of the `k`th powers of the primes up to and including `x`, modulo `modulus`.
+///
+/// Small values of `x` are handled by summing the powers of the primes found with a segmented
+/// sieve. Larger values use Lucy's algorithm, which starts from the sums of the `k`th powers of
+/// all the numbers up to each value `x / n`, and removes the composite numbers one prime at a
+/// time, taking `O(x^(3/4))` time and `O(sqrt(x))` memory. Taking `k = 0` counts the primes up to
+/// `x`.
+///
+/// # Panics
+///
+/// Panics if `modulus` is 0.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(primesieve::prime_power_sum(10, 2, 1000), (4 + 9 + 25 + 49) % 1000);
+/// assert_eq!(primesieve::prime_power_sum(1000000000, 0, u64::MAX), 50847534);
+/// assert_eq!(primesieve::prime_power_sum(10000000000, 2, 1000000007), 659676487);
+/// ```
+pub fn prime_power_sum(x: u64, k: u32, modulus: u64) -> u64 {
+    assert!(modulus > 0, "the modulus must be positive");
+    let m = modulus;
+
+    if x < SIEVE_THRESHOLD {
+        let mut sum = 0;
+        for_each_prime(x, |p| sum = add_mod(sum, pow_mod(p, k, m), m));
+        return sum;
+    }
+
+    let powers = PowerSums::new(k, m);
+    lucy(x, |v| powers.sum(v), |p| pow_mod(p, k, m), |s, w, t, u| {
+        sub_mod(s, mul_mod(w, sub_mod(t, u, m), m), m)
+    })
+}
+
+/// Calculates the sums `2^k + 3^k + ... + v^k` modulo `m`.
+///
+/// These are found using the identity that `n^k` is the sum over `j` of `S(k, j) j! C(n, j)`,
+/// where the `S(k, j)` are Stirling numbers of the second kind, so that summing over `n` from 0
+/// to `v` gives the sum over `j` of `S(k, j) j! C(v + 1, j + 1)`. The product `j! C(v + 1, j + 1)`
+/// is that of the `j + 1` consecutive numbers ending at `v + 1`, divided by `j + 1`, and exactly
+/// one of those numbers is divisible by `j + 1`, so no division modulo `m` is needed.
+struct PowerSums {
+    /// The values of `S(k, j)` modulo `m`, for `j` from 0 to `k`.
+    stirling: Vec<u64>,
+    /// The power being summed.
+    k: u32,
+    /// The modulus.
+    m: u64,
+}
+
+impl PowerSums {
+    fn new(k: u32, m: u64) -> PowerSums {
+        // Use the recurrence `S(n, j) = j S(n - 1, j) + S(n - 1, j - 1)`.
+        let mut stirling = vec![0; k as usize + 1];
+        stirling[0] = 1 % m;
+        for n in 1..=k as usize {
+            for j in (1..=n).rev() {
+                stirling[j] = add_mod(mul_mod(j as u64, stirling[j], m), stirling[j - 1], m);
+            }
+            stirling[0] = 0;
+        }
+        PowerSums { stirling, k, m }
+    }
+
+    /// Returns `2^k + 3^k + ... + v^k` modulo `m`.
+    fn sum(&self, v: u64) -> u64 {
+        let m = self.m;
+        let mut sum = 0;
+        for (j, &s) in self.stirling.iter().enumerate() {
+            let j = j as u64;
+            if s == 0 || j > v {
+                continue;
+            }
+            let mut product = 1 % m;
+            for n in v - j + 1..=v + 1 {
+                let n = if n % (j + 1) == 0 { n / (j + 1) } else { n };
+                product = mul_mod(product, n % m, m);
+            }
+            sum = add_mod(sum, mul_mod(s, product, m), m);
+        }
+
+        // Remove the terms for 0 and 1 from the sum.
+        let excluded = if self.k == 0 { 2 } else { 1 };
+        sub_mod(sum, excluded % m, m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_sums() {
+        for &m in &[1, 2, 6, 1000000007, u64::MAX] {
+            for k in 0..6 {
+                let sums = PowerSums::new(k, m);
+                let mut expected = 0;
+                for v in 2..500 {
+                    expected = (expected + pow_mod(v, k, m) as u128) % m as u128;
+                    assert_eq!(sums.sum(v), expected as u64);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_matches_sieve() {
+        for &x in &[0, 1, 2, 3, 10, 1000, 65536, 999999, SIEVE_THRESHOLD, 3000000] {
+            let mut sum = 0;
+            let mut square_sum = 0;
+            let mut count = 0;
+            for_each_prime(x, |p| {
+                sum += p as u128;
+                square_sum = (square_sum + p * p) % 1000000007;
+                count += 1;
+            });
+
+            if x > 0 {
+                let initial = |v: u64| ((v as u128 * (v as u128 + 1)) / 2).saturating_sub(1);
+                let lucy_sum = lucy(x, initial, |p| p as u128, |s, w, t, u| s - w * (t - u));
+                assert_eq!(lucy_sum, sum);
+            }
+            assert_eq!(prime_sum(x), sum);
+            assert_eq!(prime_power_sum(x, 2, 1000000007), square_sum);
+            assert_eq!(prime_power_sum(x, 0, 1 << 40), count);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Primes in arithmetic progressions, that is the primes `p` with `p ≡ a (mod q)`.
+//!
+//! Each `u64` of the sieve covers a block of 240 numbers starting at a multiple of 240, so when
+//! `q` divides 240, the residue modulo `q` of the number represented by each bit depends only on
+//! the position of the bit. The primes in a progression can then be picked out of each word of
+//! the sieve with a fixed mask, and counted without being extracted one at a time. For other
+//! moduli, the mask for the largest divisor of `q` which also divides 240 is used to skip most of
+//! the primes, and the rest are checked individually.
+
+use arith::gcd;
+use iterator::{MODULUS, OFFSETS, SMALL_PRIMES};
+use segsieve::SegmentedSieve;
+
+/// Returns the bits of a word of the sieve which represent numbers congruent to `a` modulo `q`,
+/// where `q` divides 240.
+fn residue_mask(q: u64, a: u64) -> u64 {
+    OFFSETS.iter().enumerate()
+        .filter(|&(_, &offset)| offset % q == a)
+        .fold(0, |mask, (bit, _)| mask | (1 << bit))
+}
+
+/// Returns the bits of the word of the sieve starting at `base` which represent numbers below
+/// `hi`.
+fn below_mask(base: u64, hi: u64) -> u64 {
+    if hi <= base {
+        0
+    } else if hi - base >= MODULUS {
+        !0
+    } else {
+        (1 << OFFSETS.partition_point(|&offset| offset < hi - base)) - 1
+    }
+}
+
+/// Calls `f(base, word)` for each word of the sieve covering the interval `[lo, hi)`, where `base`
+/// is the number represented by the first bit of the word, and the bits of `word` for numbers
+/// outside the interval have been turned off.
+fn for_each_word<F: FnMut(u64, u64)>(lo: u64, hi: u64, mut f: F) {
+    if hi <= lo {
+        return;
+    }
+    let mut sieve = SegmentedSieve::new(lo, hi);
+    while sieve.advance() {
+        let mut base = sieve.segment_low();
+        for &word in sieve.segment() {
+            f(base, word & below_mask(base, hi));
+            base += MODULUS;
+        }
+    }
+}
+
+/// An iterator over the primes in the interval `[lo, hi)` which are congruent to `a` modulo `q`,
+/// in increasing order.
+///
+/// # Examples
+///
+/// ```
+/// use primesieve::PrimesInProgression;
+///
+/// let primes = PrimesInProgression::new(7, 3, 0, 200).collect::<Vec<u64>>();
+/// assert_eq!(primes, vec![3, 17, 31, 59, 73, 101, 157, 199]);
+///
+/// let primes = PrimesInProgression::new(10, 1, 1000, 1100).collect::<Vec<u64>>();
+/// assert_eq!(primes, vec![1021, 1031, 1051, 1061, 1091]);
+/// ```
+pub struct PrimesInProgression {
+    /// The small primes 2, 3 and 5 in the progression, in decreasing order.
+    small: Vec<u64>,
+    /// The sieve which finds the primes in each successive segment.
+    sieve: SegmentedSieve,
+    /// The modulus of the progression.
+    modulus: u64,
+    /// The residue of the primes in the progression.
+    residue: u64,
+    /// The bits of each word of the sieve which may represent numbers in the progression.
+    mask: u64,
+    /// Whether every number represented by a bit in `mask` is in the progression.
+    exact: bool,
+    /// The end of the interval.
+    high: u64,
+    /// The index in the current segment of the current `u64`.
+    curr_idx: usize,
+    /// The bits of the current `u64` which have not yet been considered.
+    current: u64,
+    /// The number represented by the first bit of the current `u64`.
+    current_low: u64,
+}
+
+impl PrimesInProgression {
+    /// Create an iterator over the primes in `[lo, hi)` which are congruent to `a` modulo `q`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `q` is 0.
+    pub fn new(q: u64, a: u64, lo: u64, hi: u64) -> PrimesInProgression {
+        assert!(q > 0, "the modulus must be positive");
+        let a = a % q;
+        let g = gcd(q, MODULUS);
+        let small = SMALL_PRIMES.iter().rev().cloned().filter(|&p| lo <= p && p < hi && p % q == a);
+
+        PrimesInProgression {
+            small: small.collect(),
+            sieve: SegmentedSieve::new(lo, hi.max(lo)),
+            modulus: q,
+            residue: a,
+            mask: residue_mask(g, a % g),
+            exact: g == q,
+            high: hi,
+            curr_idx: 0,
+            current: 0,
+            current_low: 0,
+        }
+    }
+}
+
+impl Iterator for PrimesInProgression {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if let Some(p) = self.small.pop() {
+            return Some(p);
+        }
+
+        loop {
+            // Find the next `u64` with any bits in the mask, sieving another segment whenever we
+            // reach the end of one.
+            while self.current == 0 {
+                self.curr_idx += 1;
+                while self.curr_idx >= self.sieve.segment().len() {
+                    if !self.sieve.advance() {
+                        return None;
+                    }
+                    self.curr_idx = 0;
+                }
+                self.current = self.sieve.segment()[self.curr_idx] & self.mask;
+                self.current_low = self.sieve.segment_low() + MODULUS * self.curr_idx as u64;
+            }
+
+            let bit = self.current.trailing_zeros();
+            self.current &= self.current - 1;
+            let p = self.current_low + OFFSETS[bit as usize];
+            if p >= self.high {
+                return None;
+            }
+            if self.exact || p % self.modulus == self.residue {
+                return Some(p);
+            }
+        }
+    }
+}
+
+/// Returns the number of primes in the interval `[lo, hi)` which are congruent to `a` modulo `q`.
+///
+/// When `q` divides 240, the primes are counted a whole word of the sieve at a time by masking,
+/// and otherwise they are checked individually.
+///
+/// # Panics
+///
+/// Panics if `q` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use primesieve::count_primes_in_progression;
+///
+/// assert_eq!(count_primes_in_progression(4, 1, 0, 1000), 80);
+/// assert_eq!(count_primes_in_progression(4, 3, 0, 1000), 87);
+/// assert_eq!(count_primes_in_progression(7, 4, 1000000000, 1001000000), 8093);
+/// ```
+pub fn count_primes_in_progression(q: u64, a: u64, lo: u64, hi: u64) -> u64 {
+    assert!(q > 0, "the modulus must be positive");
+    let a = a % q;
+    if MODULUS % q != 0 {
+        return PrimesInProgression::new(q, a, lo, hi).count() as u64;
+    }
+
+    let mut count = SMALL_PRIMES.iter().filter(|&&p| lo <= p && p < hi && p % q == a).count();
+    let mask = residue_mask(q, a);
+    for_each_word(lo, hi, |_, word| count += (word & mask).count_ones() as usize);
+    count as u64
+}
+
+/// Returns the number of primes in the interval `[lo, hi)` in each residue class modulo `q`, so
+/// that the entry at index `a` of the result counts the primes congruent to `a` modulo `q`.
+///
+/// All of the counts are found with a single run of the sieve. When `q` divides 240, each word of
+/// the sieve is split between the residue classes by masking, and otherwise the primes are
+/// assigned to their classes individually.
+///
+/// # Panics
+///
+/// Panics if `q` is 0.
+///
+/// # Examples
+///
+/// ```
+/// let counts = primesieve::count_primes_by_residue(10, 0, 1000);
+/// assert_eq!(counts, vec![0, 40, 1, 42, 0, 1, 0, 46, 0, 38]);
+///
+/// let counts = primesieve::count_primes_by_residue(7, 1000000000, 1001000000);
+/// assert_eq!(counts, vec![0, 8089, 8010, 7958, 8093, 8021, 7984]);
+/// ```
+pub fn count_primes_by_residue(q: u64, lo: u64, hi: u64) -> Vec<u64> {
+    assert!(q > 0, "the modulus must be positive");
+    let mut counts = vec![0; q as usize];
+    for &p in SMALL_PRIMES.iter().filter(|&&p| lo <= p && p < hi) {
+        counts[(p % q) as usize] += 1;
+    }
+
+    if MODULUS % q == 0 {
+        let masks = (0..q)
+            .map(|a| (a as usize, residue_mask(q, a)))
+            .filter(|&(_, mask)| mask != 0)
+            .collect::<Vec<_>>();
+        for_each_word(lo, hi, |_, word| {
+            for &(a, mask) in &masks {
+                counts[a] += (word & mask).count_ones() as u64;
+            }
+        });
+    } else {
+        for_each_word(lo, hi, |base, mut word| {
+            while word != 0 {
+                let p = base + OFFSETS[word.trailing_zeros() as usize];
+                counts[(p % q) as usize] += 1;
+                word &= word - 1;
+            }
+        });
+    }
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sieve::Sieve;
+
+    #[test]
+    fn test_matches_sieve() {
+        let limit = 10000000;
+        let sieve = Sieve::to_limit(limit);
+        let primes = sieve.iter().take_while(|&p| p < limit).collect::<Vec<u64>>();
+        for &(lo, hi) in &[(0, 0), (0, 1000), (3, 6), (1000, 999), (7864000, 7865001),
+                           (12345, limit - 1)] {
+            let window = primes.iter().cloned().filter(|&p| lo <= p && p < hi).collect::<Vec<_>>();
+            for &q in &[1, 2, 4, 7, 10, 16, 48, 60, 97, 240, 1000] {
+                let mut expected = vec![0; q as usize];
+                for &p in &window {
+                    expected[(p % q) as usize] += 1;
+                }
+                assert_eq!(count_primes_by_residue(q, lo, hi), expected);
+
+                for a in [0, 1, 3, 5, q - 1, q + 1].iter().cloned() {
+                    let progression = window.iter().cloned().filter(|&p| p % q == a % q);
+                    let progression = progression.collect::<Vec<_>>();
+                    assert_eq!(PrimesInProgression::new(q, a, lo, hi).collect::<Vec<_>>(),
+                               progression);
+                    assert_eq!(count_primes_in_progression(q, a, lo, hi),
+                               progression.len() as u64);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Arithmetic functions, such as Euler's totient function, evaluated at every integer in an
+//! interval using a segmented sieve.
+//!
+//! Each function returns an iterator which yields the value of the function at each integer in
+//! the interval `[lo, hi)` in turn, skipping 0. The values are calculated one segment at a time
+//! from the prime power divisors found by the sieve, so that the memory used is proportional to
+//! the square root of `hi`, and summing a function over billions of integers is feasible.
+
+use arith::divisor_power_sum;
+use factorisations::SegmentFactoriser;
+
+/// An iterator over the values of a function at the integers in an interval, where the value at
+/// each integer is built up from its prime power divisors.
+struct RangeValues<T, F> {
+    /// The sieve which factorises the numbers in each segment.
+    factoriser: SegmentFactoriser,
+    /// The values of the function at the numbers in the current segment.
+    values: Vec<T>,
+    /// The index in the current segment of the next value to yield.
+    idx: usize,
+    /// The value of the function at 1.
+    initial: T,
+    /// Updates the value at a number to account for a prime `p` which divides it exactly `k`
+    /// times.
+    update: F,
+}
+
+impl<T: Clone, F: Fn(&mut T, u64, u64)> RangeValues<T, F> {
+    fn new(lo: u64, hi: u64, initial: T, update: F) -> RangeValues<T, F> {
+        RangeValues {
+            factoriser: SegmentFactoriser::new(lo, hi),
+            values: Vec::new(),
+            idx: 0,
+            initial,
+            update,
+        }
+    }
+}
+
+impl<T: Clone, F: Fn(&mut T, u64, u64)> Iterator for RangeValues<T, F> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        // Calculate the values for another segment if we have reached the end of the current one.
+        if self.idx == self.values.len() {
+            self.values.clear();
+            self.values.resize(self.factoriser.next_segment_len(), self.initial.clone());
+            let (values, update) = (&mut self.values, &self.update);
+            self.factoriser.next_segment(|idx, p, k| update(&mut values[idx], p, k))?;
+            self.idx = 0;
+        }
+
+        self.idx += 1;
+        Some(self.values[self.idx - 1].clone())
+    }
+}
+
+/// Returns an iterator over the values of Euler's totient function `ϕ(n)` for `n` in `[lo, hi)`.
+///
+/// The sum of `ϕ(n)` over `n <= x` is about `3x^2 / π^2`, which overflows a `u64` once `x` passes
+/// about `7.8 * 10^9`, so sums over large ranges should be accumulated as `u128`.
+///
+/// # Examples
+///
+/// ```
+/// let phi = primesieve::euler_phi_range(1, 13).collect::<Vec<u64>>();
+/// assert_eq!(phi, vec![1, 1, 2, 2, 4, 2, 6, 4, 6, 4, 10, 4]);
+///
+/// let sum = primesieve::euler_phi_range(0, 1000001).map(u128::from).sum::<u128>();
+/// assert_eq!(sum, 303963552392);
+/// ```
+pub fn euler_phi_range(lo: u64, hi: u64) -> impl Iterator<Item = u64> {
+    RangeValues::new(lo, hi, 1, |value: &mut u64, p, k| *value *= (p - 1) * p.pow(k as u32 - 1))
+}
+
+/// Returns an iterator over the values of the Möbius function `μ(n)` for `n` in `[lo, hi)`.
+///
+/// # Examples
+///
+/// ```
+/// let mu = primesieve::mobius_range(1, 13).collect::<Vec<i64>>();
+/// assert_eq!(mu, vec![1, -1, -1, 0, -1, 1, -1, 0, 0, 1, -1, 0]);
+/// ```
+pub fn mobius_range(lo: u64, hi: u64) -> impl Iterator<Item = i64> {
+    RangeValues::new(lo, hi, 1, |value: &mut i64, _, k| *value *= if k == 1 { -1 } else { 0 })
+}
+
+/// Returns an iterator over the number of divisors `τ(n)` of each `n` in `[lo, hi)`.
+///
+/// # Examples
+///
+/// ```
+/// let tau = primesieve::number_of_divisors_range(1, 13).collect::<Vec<u64>>();
+/// assert_eq!(tau, vec![1, 2, 2, 3, 2, 4, 2, 4, 3, 4, 2, 6]);
+/// ```
+pub fn number_of_divisors_range(lo: u64, hi: u64) -> impl Iterator<Item = u64> {
+    RangeValues::new(lo, hi, 1, |value: &mut u64, _, k| *value *= k + 1)
+}
+
+/// Returns an iterator over the sums `σ_k(n)` of the `k`th powers of the divisors of each `n` in
+/// `[lo, hi)`.
+///
+/// The values are given as `u128`, which is large enough for `k = 1` and `k = 2` for every `n`,
+/// but which may overflow for larger `k`.
+///
+/// # Examples
+///
+/// ```
+/// let sigma = primesieve::divisor_sum_range(1, 13, 1).collect::<Vec<u128>>();
+/// assert_eq!(sigma, vec![1, 3, 4, 7, 6, 12, 8, 15, 13, 18, 12, 28]);
+///
+/// let sigma_0 = primesieve::divisor_sum_range(1, 13, 0).collect::<Vec<u128>>();
+/// assert_eq!(sigma_0, vec![1, 2, 2, 3, 2, 4, 2, 4, 3, 4, 2, 6]);
+///
+/// assert_eq!(primesieve::divisor_sum_range(10, 11, 2).next(), Some(1 + 4 + 25 + 100));
+/// ```
+pub fn divisor_sum_range(lo: u64, hi: u64, k: u32) -> impl Iterator<Item = u128> {
+    RangeValues::new(lo, hi, 1, move |value: &mut u128, p, e| *value *= divisor_power_sum(p, k, e))
+}
+
+/// Returns an iterator over the number of distinct prime factors `ω(n)` of each `n` in
+/// `[lo, hi)`.
+///
+/// # Examples
+///
+/// ```
+/// let omega = primesieve::omega_range(1, 13).collect::<Vec<u64>>();
+/// assert_eq!(omega, vec![0, 1, 1, 1, 1, 2, 1, 1, 1, 2, 1, 2]);
+/// ```
+pub fn omega_range(lo: u64, hi: u64) -> impl Iterator<Item = u64> {
+    RangeValues::new(lo, hi, 0, |value: &mut u64, _, _| *value += 1)
+}
+
+/// Returns an iterator over the number of prime factors `Ω(n)`, counted with multiplicity, of
+/// each `n` in `[lo, hi)`.
+///
+/// # Examples
+///
+/// ```
+/// let big_omega = primesieve::big_omega_range(1, 13).collect::<Vec<u64>>();
+/// assert_eq!(big_omega, vec![0, 1, 1, 2, 1, 2, 1, 3, 2, 2, 1, 3]);
+/// ```
+pub fn big_omega_range(lo: u64, hi: u64) -> impl Iterator<Item = u64> {
+    RangeValues::new(lo, hi, 0, |value: &mut u64, _, k| *value += k)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sieve::Sieve;
+
+    #[test]
+    fn test_matches_sieve() {
+        let sieve = Sieve::to_limit(1000);
+        for &(lo, hi) in &[(0, 300000), (1000000000000, 1000000100000)] {
+            let phi = euler_phi_range(lo, hi).collect::<Vec<_>>();
+            let mu = mobius_range(lo, hi).collect::<Vec<_>>();
+            let tau = number_of_divisors_range(lo, hi).collect::<Vec<_>>();
+            let sigma = divisor_sum_range(lo, hi, 1).collect::<Vec<_>>();
+            let omega = omega_range(lo, hi).collect::<Vec<_>>();
+            let big_omega = big_omega_range(lo, hi).collect::<Vec<_>>();
+            assert_eq!(phi.len() as u64, hi - lo.max(1));
+
+            for (idx, n) in (lo.max(1)..hi).enumerate() {
+                let factors = sieve.factorise(n).unwrap();
+                let squarefree = factors.is_squarefree();
+                let divisor_sum = factors.iter()
+                    .map(|&(p, k)| ((p as u128).pow(k as u32 + 1) - 1) / (p as u128 - 1))
+                    .product::<u128>();
+
+                assert_eq!(phi[idx], sieve.euler_phi(n).unwrap());
+                assert_eq!(mu[idx], if squarefree { (-1i64).pow(factors.len() as u32) } else { 0 });
+                assert_eq!(tau[idx], sieve.number_of_divisors(n).unwrap());
+                assert_eq!(sigma[idx], divisor_sum);
+                assert_eq!(omega[idx], factors.len() as u64);
+                assert_eq!(big_omega[idx], factors.iter().map(|&(_, k)| k).sum::<u64>());
+            }
+        }
+    }
+}
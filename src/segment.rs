@@ -20,6 +20,7 @@ pub const MODULUS: u64 = 240;
 /// Calculate the internal index at which the bit for a given index into the range is found.
 #[inline]
 fn index_for(idx: u64) -> (bool, usize, u64) {
+    #[allow(clippy::redundant_static_lifetimes)]
     const POS: &'static [(bool, u64); MODULUS as usize] =
           // 0
         &[(false, 1 << 0), (true, 1 << 0), (false, 1 << 1), (false, 1 << 1), (false, 1 << 1),
@@ -89,7 +90,7 @@ fn index_for(idx: u64) -> (bool, usize, u64) {
 pub fn get(segment: &[u64], idx: u64) -> bool {
     match index_for(idx) {
         (false, _, _) => false,
-        (true, x, y) => (segment[x] & y != 0),
+        (true, x, y) => segment[x] & y != 0,
     }
 }
 
@@ -111,11 +112,37 @@ pub fn set_on(segment: &mut [u64], idx: u64) {
     }
 }
 
+/// Set the bits representing all numbers at indices strictly below the given index to off.
+pub fn clear_below(segment: &mut [u64], idx: u64) {
+    // The bit returned by `index_for` is always that of the smallest number at or above the given
+    // index, so every lower bit in the same `u64` belongs to a number below the index.
+    let (_, x, y) = index_for(idx);
+    let x = x.min(segment.len());
+    for word in &mut segment[..x] {
+        *word = 0;
+    }
+    if x < segment.len() {
+        segment[x] &= !(y - 1);
+    }
+}
+
+/// Count the bits which are on for numbers at indices strictly below the given index.
+pub fn count_below(segment: &[u64], idx: u64) -> u64 {
+    let (_, x, y) = index_for(idx);
+    let x = x.min(segment.len());
+    let mut count = segment[..x].iter().map(|word| word.count_ones() as u64).sum();
+    if x < segment.len() {
+        count += (segment[x] & (y - 1)).count_ones() as u64;
+    }
+    count
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_bool)]
     fn set_small_values() {
         for ix in 0..MODULUS {
             let mut segment = [!0; 1];
@@ -132,6 +159,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison, clippy::needless_bool)]
     fn set_large_values() {
         for ix in 0..MODULUS {
             let mut segment = [!0; 100];
@@ -146,4 +174,29 @@ mod tests {
             assert_eq!(get(&segment, ix + 99 * 30), expected);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn clear_small_values() {
+        for ix in 0..3 * MODULUS {
+            let mut segment = [!0; 3];
+            clear_below(&mut segment, ix);
+            for jx in 0..3 * MODULUS {
+                let coprime = (jx % 2 != 0) && (jx % 3 != 0) && (jx % 5 != 0);
+                assert_eq!(get(&segment, jx), coprime && jx >= ix);
+            }
+        }
+    }
+
+    #[test]
+    fn count_small_values() {
+        let segment = [!0; 3];
+        let mut expected = 0;
+        for ix in 0..3 * MODULUS {
+            assert_eq!(count_below(&segment, ix), expected);
+            if (ix % 2 != 0) && (ix % 3 != 0) && (ix % 5 != 0) {
+                expected += 1;
+            }
+        }
+        assert_eq!(count_below(&segment, 1000), 64 * 3);
+    }
+}
@@ -1,14 +1,19 @@
 //! An implementation of the segmented sieve of Eratosthenes.
 
-use std::cmp::min;
+use std::cmp::{max, min};
+use std::collections::VecDeque;
 use std::slice::from_raw_parts_mut;
+use std::sync::Arc;
+use std::thread;
 
 use iterator::SieveIterator;
-use segment::set_off;
+use presieve::{presieve, PRESIEVE_PRIMES};
+use segment::{clear_below, set_off, set_on};
 use wheel::Wheel30;
 
 const MODULUS: u64 = 240;
-const SEGMENT_LEN: usize = 32768;
+/// The length of each segment in `u64`s, which is also the longest segment that can be pre-sieved.
+pub const SEGMENT_LEN: usize = 32768;
 const SEGMENT_SIZE: u64 = MODULUS * SEGMENT_LEN as u64;
 
 /// Returns a sequence of `u64`s encoding the primes up to the square root of the given limit, but
@@ -50,61 +55,280 @@ fn small_primes(limit: u64) -> Vec<u64> {
     sieve
 }
 
-/// Sieve primes up to the given limit using a segmented sieve of Eratosthenes, and return a
-/// vector of `u64`s encoding the primes.
-pub fn segmented_sieve(limit: u64) -> Vec<u64> {
-    // First, we need to sieve the primes up to the square root of the given limit - these will be
-    // the primes whose multiples are crossed off the sieve.
-    let lim = limit + MODULUS - (limit % MODULUS);
-    let small_primes = small_primes(lim);
-    let mut small_primes_iter = SieveIterator::new(&small_primes);
-
-    // Here's the array in which we'll do our sieving of the segments, and a vector in which we'll
-    // store the final results.
-    let mut segment = [!0; SEGMENT_LEN];
-    segment[0] ^= 1;
-    let mut segments = Vec::with_capacity((lim / MODULUS) as usize);
-
-    // Here are the indices into the segment for the next multiple of each prime whose multiples
-    // are being crossed off - the first entry is the index, and the second entry is a wheel which
-    // generates the differences between successive indices.
-    let mut next_indices = Vec::<(u64, Wheel30)>::new();
-
-    // Iterate over segments for as long as we still have more sieving to do.
-    let mut low = 0;
-    while low <= lim {
-
-        // Now, add the new sieving primes which we will need for this segment.
-        let high = min(low + SEGMENT_SIZE, lim);
+/// Returns the smallest multiple of `prime` which is at least `prime * prime`, is at least `low`
+/// and is not a multiple of 2, 3 or 5, along with a wheel generating the differences between it
+/// and the subsequent such multiples.
+fn first_multiple(prime: u64, low: u64) -> (u64, Wheel30) {
+    let mut mult = max(prime, low.div_ceil(prime));
+    while mult % 2 == 0 || mult % 3 == 0 || mult % 5 == 0 {
+        mult += 1;
+    }
+    // If there is no such multiple below 2^64, then `u64::MAX` stands in for it instead. This is
+    // divisible by 3, so it is never represented in a segment.
+    (prime.saturating_mul(mult), Wheel30::new(prime, mult))
+}
+
+/// Returns the primes, other than 2, 3 and 5, which are needed to sieve for primes up to the given
+/// limit.
+pub fn sieving_primes(limit: u64) -> Vec<u64> {
+    SieveIterator::new(&small_primes(limit)).collect()
+}
+
+/// Returns the number at which the segments covering the interval `[low, high)` end.
+///
+/// The segments stop short of the last partial block of 240 numbers below 2^64, which holds no
+/// primes.
+fn segments_end(low: u64, high: u64) -> u64 {
+    let last = u64::MAX - u64::MAX % MODULUS;
+    min(max(high, low - low % MODULUS), last).div_ceil(MODULUS) * MODULUS
+}
+
+/// The state of a segmented sieve of Eratosthenes, which sieves the primes in a range one segment
+/// at a time.
+pub struct SegmentedSieve {
+    /// The primes, other than 2, 3 and 5, which are available for crossing off multiples. These
+    /// may be shared between several sieves working on different parts of the same range.
+    sieving_primes: Arc<Vec<u64>>,
+    /// The number of entries at the start of `sieving_primes` which are already in use.
+    num_active: usize,
+    /// The limit passed to `small_primes` when finding the current set of sieving primes - these
+    /// suffice to sieve any segment ending no later than this.
+    primes_limit: u64,
+    /// Here are the indices into the segment for the next multiple of each prime whose multiples
+    /// are being crossed off - the first entry is the index, and the second entry is a wheel which
+    /// generates the differences between successive indices.
+    next_indices: Vec<(u64, Wheel30)>,
+    /// The sieving primes which are larger than a segment, and so have at most one multiple in
+    /// any given segment, are kept in buckets according to which segment their next multiple
+    /// lies in - the first bucket is for the next segment to be sieved, the second for the one
+    /// after that, and so on. Each entry holds the next multiple itself, and a wheel which
+    /// generates the differences between successive multiples.
+    buckets: VecDeque<Vec<(u64, Wheel30)>>,
+    /// The array in which the sieving of each segment is done.
+    segment: Vec<u64>,
+    /// The number of `u64`s of `segment` which are part of the current segment.
+    segment_len: usize,
+    /// The number represented by the first bit of the current segment.
+    segment_low: u64,
+    /// The number represented by the first bit of the next segment.
+    next_low: u64,
+    /// The number at which sieving stops.
+    end: u64,
+    /// The index of the first number in the next segment whose bit should be kept - this is only
+    /// non-zero before the first segment is sieved.
+    offset: u64,
+}
+
+impl SegmentedSieve {
+    /// Create a `SegmentedSieve` which will sieve the primes in the interval `[low, high)`.
+    ///
+    /// The segments begin at the largest multiple of 240 not exceeding `low` and end at the
+    /// smallest multiple of 240 not less than `high`. Any bits for numbers below `low` are set to
+    /// off, but there may be primes encoded which are at least `high`.
+    pub fn new(low: u64, high: u64) -> SegmentedSieve {
+        let end = segments_end(low, high);
+        SegmentedSieve::with_primes_limit(low, end, Arc::new(sieving_primes(end)), end)
+    }
+
+    /// Create a `SegmentedSieve` which will sieve the primes in the interval `[low, high)` in the
+    /// same way as `SegmentedSieve::new`, but using a table of sieving primes, as returned by
+    /// `sieving_primes`, which has already been computed for some limit not less than `high`.
+    pub fn with_sieving_primes(low: u64, high: u64, primes: Arc<Vec<u64>>) -> SegmentedSieve {
+        let end = segments_end(low, high);
+        SegmentedSieve::with_primes_limit(low, end, primes, end)
+    }
+
+    /// Create a `SegmentedSieve` which will sieve all primes from `low` onwards, without any upper
+    /// limit.
+    pub fn unbounded(low: u64) -> SegmentedSieve {
+        let end = u64::MAX - u64::MAX % MODULUS;
+        let primes_limit = low.saturating_add(SEGMENT_SIZE);
+        let primes = Arc::new(sieving_primes(primes_limit));
+        SegmentedSieve::with_primes_limit(low, end, primes, primes_limit)
+    }
+
+    /// Create a `SegmentedSieve` for the interval from `low` to `end`, where `end` is a multiple
+    /// of 240, using sieving primes which are enough to reach `primes_limit`.
+    fn with_primes_limit(low: u64, end: u64, primes: Arc<Vec<u64>>, primes_limit: u64)
+                         -> SegmentedSieve {
+        let start = low - low % MODULUS;
+        SegmentedSieve {
+            sieving_primes: primes,
+            num_active: 0,
+            primes_limit,
+            next_indices: Vec::new(),
+            buckets: VecDeque::new(),
+            segment: vec![!0; SEGMENT_LEN],
+            segment_len: 0,
+            segment_low: start,
+            next_low: start,
+            end,
+            offset: low - start,
+        }
+    }
+
+    /// Returns the encoding of the primes in the current segment.
+    pub fn segment(&self) -> &[u64] {
+        &self.segment[..self.segment_len]
+    }
+
+    /// Returns the number represented by the first bit of the current segment.
+    pub fn segment_low(&self) -> u64 {
+        self.segment_low
+    }
+
+    /// Find more sieving primes, so that there are enough to sieve up to the given number.
+    fn grow_sieving_primes(&mut self, high: u64) {
+        let limit = max(high, self.primes_limit.saturating_mul(4));
+        let largest = self.sieving_primes.last().cloned().unwrap_or(0);
+        let small_primes = small_primes(limit);
+        let new_primes = SieveIterator::new(&small_primes).skip_while(|&p| p <= largest);
+        Arc::make_mut(&mut self.sieving_primes).extend(new_primes);
+        self.primes_limit = limit;
+    }
+
+    /// Put a multiple of one of the large sieving primes into the bucket for the segment in which
+    /// it lies, where `low` is the start of the next segment to be sieved. Multiples beyond the
+    /// end of the sieve are dropped, since they are never needed.
+    fn push_to_bucket(&mut self, low: u64, multiple: u64, wheel: Wheel30) {
+        if multiple >= self.end {
+            return;
+        }
+        let ix = ((multiple - low) / SEGMENT_SIZE) as usize;
+        if self.buckets.len() <= ix {
+            self.buckets.resize_with(ix + 1, Vec::new);
+        }
+        self.buckets[ix].push((multiple, wheel));
+    }
+
+    /// Sieve the next segment, returning `false` if there are no more segments to sieve.
+    pub fn advance(&mut self) -> bool {
+        if self.next_low >= self.end {
+            return false;
+        }
+
+        let low = self.next_low;
+        let high = min(low.saturating_add(SEGMENT_SIZE), self.end);
         let segment_size = high - low;
 
-        while let Some(prime) = small_primes_iter.next() {
-            next_indices.push((prime * prime - low, Wheel30::new(prime, prime)));
-            if prime * prime >= high {
+        // Now, add the new sieving primes which we will need for this segment, finding some more
+        // if we have run out. Those which are larger than a segment go into the buckets, those
+        // used for pre-sieving are not needed at all, and the rest are crossed off in every
+        // segment.
+        if high > self.primes_limit {
+            self.grow_sieving_primes(high);
+        }
+        while let Some(&prime) = self.sieving_primes.get(self.num_active) {
+            if prime.saturating_mul(prime) >= high {
                 break;
             }
+            self.num_active += 1;
+            if PRESIEVE_PRIMES.contains(&prime) {
+                continue;
+            }
+            let (multiple, wheel) = first_multiple(prime, low);
+            if prime > SEGMENT_SIZE {
+                self.push_to_bucket(low, multiple, wheel);
+            } else {
+                self.next_indices.push((multiple - low, wheel));
+            }
+        }
+
+        // Prepare a fresh segment with the multiples of the smallest primes already removed. The
+        // number 1 is not prime, but is not crossed off by any sieving prime, while the primes
+        // used for pre-sieving are crossed off, so deal with these separately.
+        let segment_len = (segment_size / MODULUS) as usize;
+        presieve(&mut self.segment[..segment_len], low);
+        if low == 0 {
+            self.segment[0] ^= 1;
+            for &prime in PRESIEVE_PRIMES {
+                set_on(&mut self.segment, prime);
+            }
         }
 
         // Sieve the current segment
-        for &mut (ref mut index, ref mut wheel) in &mut next_indices {
+        for &mut (ref mut index, ref mut wheel) in &mut self.next_indices {
             while *index < segment_size {
-                set_off(&mut segment, *index);
+                set_off(&mut self.segment, *index);
                 *index += wheel.next_diff();
             }
             *index -= segment_size;
         }
 
-        // Store the result of this pass and prepare for the next pass.
-        segments.extend_from_slice(
-            if segment_size < SEGMENT_SIZE {
-                &segment[..(segment_size / MODULUS) as usize]
-            } else {
-                &segment
+        // Cross off the multiples of those large primes which hit this segment, and move each of
+        // them into the bucket for the segment containing its next multiple.
+        let bucket = self.buckets.pop_front().unwrap_or_default();
+        for (multiple, mut wheel) in bucket {
+            set_off(&mut self.segment, multiple - low);
+            if let Some(next) = multiple.checked_add(wheel.next_diff()) {
+                self.push_to_bucket(high, next, wheel);
             }
-        );
+        }
+
+        // Throw away anything below the start of the range we were asked for.
+        clear_below(&mut self.segment, self.offset);
+        self.offset = 0;
 
-        low += SEGMENT_SIZE;
-        segment = [!0; SEGMENT_LEN];
+        self.segment_low = low;
+        self.segment_len = segment_len;
+        self.next_low = high;
+        true
+    }
+}
+
+/// Sieve primes up to the given limit using a segmented sieve of Eratosthenes, and return a
+/// vector of `u64`s encoding the primes.
+pub fn segmented_sieve(limit: u64) -> Vec<u64> {
+    segmented_sieve_range(0, limit + 1)
+}
+
+/// Sieve the primes in the interval `[low, high)` using a segmented sieve of Eratosthenes, and
+/// return a vector of `u64`s encoding the primes.
+///
+/// The encoding covers the same range as the segments of `SegmentedSieve::new(low, high)`.
+pub fn segmented_sieve_range(low: u64, high: u64) -> Vec<u64> {
+    let mut sieve = SegmentedSieve::new(low, high);
+    let mut segments = Vec::with_capacity(((sieve.end - sieve.next_low) / MODULUS) as usize);
+    while sieve.advance() {
+        segments.extend_from_slice(sieve.segment());
+    }
+
+    segments
+}
+
+/// Sieve primes up to the given limit using a segmented sieve of Eratosthenes spread across the
+/// given number of threads, and return a vector of `u64`s encoding the primes.
+///
+/// The range is split into contiguous blocks of whole segments, one for each thread, which share
+/// a single table of sieving primes. The result is identical to that of `segmented_sieve`.
+pub fn segmented_sieve_parallel(limit: u64, threads: usize) -> Vec<u64> {
+    let end = segments_end(0, limit + 1);
+    let primes = Arc::new(sieving_primes(end));
+
+    // Work out how many segments each thread should sieve.
+    let num_segments = end.div_ceil(SEGMENT_SIZE);
+    let block_size = num_segments.div_ceil(max(threads, 1) as u64) * SEGMENT_SIZE;
+
+    // Set each thread off sieving its own block, and stitch the results together in order.
+    let handles = (0..end.div_ceil(block_size))
+        .map(|ix| {
+            let low = ix * block_size;
+            let high = min(low + block_size, end);
+            let primes = primes.clone();
+            thread::spawn(move || {
+                let mut sieve = SegmentedSieve::with_sieving_primes(low, high, primes);
+                let mut segments = Vec::with_capacity(((high - low) / MODULUS) as usize);
+                while sieve.advance() {
+                    segments.extend_from_slice(sieve.segment());
+                }
+                segments
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut segments = Vec::with_capacity((end / MODULUS) as usize);
+    for handle in handles {
+        segments.extend(handle.join().unwrap());
     }
 
     segments
@@ -135,6 +359,7 @@ fn test_small_primes() {
 mod tests {
     use super::*;
     use iterator::SieveIterator;
+    use sieve::Sieve;
 
     #[test]
     fn test_small_segmented_sieve() {
@@ -178,4 +403,56 @@ mod tests {
                         49999991, 50000017, 50000021, 50000047, 50000059, 50000063, 50000101,
                         50000131, 50000141]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_range_segmented_sieve() {
+        let sieve = segmented_sieve(10000000);
+        let all = SieveIterator::new(&sieve).collect::<Vec<u64>>();
+        for &(low, high) in &[(0, 1000), (7, 8), (240, 480), (1000, 2000), (12345, 9876543)] {
+            let sieve = segmented_sieve_range(low, high);
+            let start = low - low % MODULUS;
+            let primes = SieveIterator::with_offset(&sieve, start)
+                .take_while(|&p| p < high)
+                .collect::<Vec<u64>>();
+            let expected = all.iter()
+                .cloned()
+                .filter(|&p| low <= p && p < high)
+                .collect::<Vec<u64>>();
+            assert_eq!(primes, expected);
+        }
+    }
+
+    #[test]
+    fn test_parallel_segmented_sieve() {
+        for &limit in &[0, 1000, 7864319, 7864320, 30000000] {
+            let sieve = segmented_sieve(limit);
+            for threads in 0..5 {
+                assert_eq!(segmented_sieve_parallel(limit, threads), sieve);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bucket_segmented_sieve() {
+        // The primes above the segment size are only used when sieving beyond about 6 * 10^13.
+        let low = 100000000000000;
+        let high = low + 50000000;
+        let sieve = segmented_sieve_range(low, high);
+        let start = low - low % MODULUS;
+        let primes = SieveIterator::with_offset(&sieve, start).take_while(|&p| p < high);
+        let (count, sum) = primes.fold((0, 0u64), |(c, s), p| (c + 1, s.wrapping_add(p)));
+        assert_eq!((count, sum), (1551591, 7585186192449127243));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_top_segmented_sieve() {
+        // Sieving just below 2^64 needs all the primes below 2^32, which takes a while. Neither
+        // the segments nor the multiples of the sieving primes may overflow.
+        let low = u64::MAX - 1000;
+        let sieve = Sieve::in_range(low, u64::MAX);
+        let gaps_to_max = sieve.iter().map(|p| u64::MAX - p).collect::<Vec<_>>();
+        assert_eq!(gaps_to_max, vec![944, 934, 898, 896, 844, 842, 824, 742, 502, 452, 424, 362,
+                                     352, 322, 278, 256, 188, 178, 94, 82, 58]);
+    }
+}
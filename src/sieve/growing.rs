@@ -0,0 +1,178 @@
+//! A `Sieve` which extends itself whenever it is asked about a prime it does not yet know about.
+
+use std::cell::{Ref, RefCell, RefMut};
+
+use bounds::nth_prime_upper_bound;
+use sieve::Sieve;
+
+/// A wrapper around a `Sieve` which extends it in place whenever a query needs more primes than
+/// it holds, so that there is no need to choose a limit up front.
+///
+/// The sieve is held behind a `RefCell`, so it can be extended through a shared reference. Each
+/// extension made on demand at least doubles the limit of the sieve, so that asking for
+/// successively larger primes only sieves each number once and resumes the sieve a logarithmic
+/// number of times.
+///
+/// # Examples
+///
+/// ```
+/// let sieve = primesieve::GrowingSieve::new();
+///
+/// assert_eq!(sieve.nth_prime(0), 2);
+/// assert_eq!(sieve.nth_prime(999), 7919);
+/// assert_eq!(sieve.nth_prime(999999), 15485863);
+/// assert!(sieve.limit() >= 15485863);
+///
+/// assert!(sieve.sieve().is_prime(15485863));
+/// ```
+pub struct GrowingSieve {
+    /// The sieve holding the primes found so far.
+    sieve: RefCell<Sieve>,
+}
+
+impl GrowingSieve {
+    /// Create a new `GrowingSieve`, which starts out knowing only about the smallest primes.
+    pub fn new() -> GrowingSieve {
+        GrowingSieve::from(Sieve::to_limit(0))
+    }
+
+    /// Extend the underlying `Sieve` so that it knows about the primes up to at least `limit`,
+    /// doing nothing if it already does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sieve needs extending while a borrow returned by `sieve` is still alive.
+    pub fn extend_to(&self, limit: u64) {
+        if limit >= self.limit() {
+            self.sieve_mut().extend_to(limit);
+        }
+    }
+
+    /// Extend the underlying `Sieve` to at least `limit`, and to at least double its current
+    /// limit.
+    fn grow(&self, limit: u64) {
+        let current = self.limit();
+        self.sieve_mut().extend_to(limit.max(current.saturating_mul(2)));
+    }
+
+    /// Mutably borrows the underlying `Sieve`, in order to extend it.
+    fn sieve_mut(&self) -> RefMut<'_, Sieve> {
+        self.sieve.try_borrow_mut().expect("GrowingSieve extended while borrowed by `sieve`")
+    }
+
+    /// Returns the `n`th prime number, indexed from 0, extending the sieve first if it does not
+    /// hold enough primes.
+    ///
+    /// If the sieve was created from one made by `Sieve::in_range`, then the primes are indexed
+    /// from the start of its interval.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sieve needs extending while a borrow returned by `sieve` is still alive.
+    pub fn nth_prime(&self, n: usize) -> u64 {
+        while n >= self.sieve.borrow().num_primes() {
+            // The bound may fall short for a sieve which starts above zero, but the doubling
+            // makes progress regardless.
+            self.grow(nth_prime_upper_bound(n as u64));
+        }
+        self.sieve.borrow().nth_prime(n).unwrap()
+    }
+
+    /// Returns the highest number that the underlying `Sieve` currently knows about.
+    pub fn limit(&self) -> u64 {
+        self.sieve.borrow().limit()
+    }
+
+    /// Returns the number of primes that the underlying `Sieve` currently knows about.
+    pub fn num_primes(&self) -> usize {
+        self.sieve.borrow().num_primes()
+    }
+
+    /// Borrows the underlying `Sieve`, to make use of the rest of its functionality.
+    ///
+    /// The sieve cannot be extended while the borrow is held, so calling `extend_to`, or calling
+    /// `nth_prime` with an index beyond the primes already known, panics until it is dropped.
+    pub fn sieve(&self) -> Ref<'_, Sieve> {
+        self.sieve.borrow()
+    }
+
+    /// Consumes this `GrowingSieve`, returning the underlying `Sieve`.
+    pub fn into_inner(self) -> Sieve {
+        self.sieve.into_inner()
+    }
+}
+
+impl Default for GrowingSieve {
+    fn default() -> GrowingSieve {
+        GrowingSieve::new()
+    }
+}
+
+impl From<Sieve> for GrowingSieve {
+    fn from(sieve: Sieve) -> GrowingSieve {
+        GrowingSieve { sieve: RefCell::new(sieve) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extend_matches_sieve() {
+        let limit = 20000000;
+        let full = Sieve::to_limit(limit);
+        for &(lo, hi) in &[(0, 0), (0, 100), (1000, 999), (1000, 7864320), (7864321, 7864400)] {
+            let mut sieve = Sieve::in_range(lo, hi);
+            for &new_limit in &[0, 500, 239, 100000, 7864320, 7864321, limit] {
+                sieve.extend_to(new_limit);
+                assert!(sieve.limit() > new_limit);
+                assert_eq!(sieve.lower_limit(), lo);
+
+                let end = sieve.limit();
+                let expected = full.iter().filter(|&p| lo <= p && p < end).collect::<Vec<_>>();
+                let primes = sieve.iter().take_while(|&p| p < end).collect::<Vec<_>>();
+                assert_eq!(primes, expected);
+                assert_eq!(sieve.num_primes(), expected.len());
+                let mid = expected.len() / 2;
+                assert_eq!(sieve.nth_prime(mid), expected.get(mid).cloned());
+            }
+        }
+    }
+
+    #[test]
+    fn test_growing_nth_prime() {
+        let full = Sieve::to_limit(2000000);
+        let sieve = GrowingSieve::new();
+        for (n, p) in full.iter().take_while(|&p| p <= 2000000).enumerate().step_by(997) {
+            assert_eq!(sieve.nth_prime(n), p);
+        }
+
+        let sieve = GrowingSieve::from(Sieve::in_range(1000000, 1000001));
+        assert_eq!(sieve.nth_prime(0), 1000003);
+        let expected = full.iter().filter(|&p| p >= 1000000).nth(1000);
+        assert_eq!(Some(sieve.nth_prime(1000)), expected);
+    }
+
+    #[test]
+    fn test_growing_extend_to() {
+        let sieve = GrowingSieve::new();
+        sieve.extend_to(1000000);
+        assert!(sieve.limit() >= 1000000 && sieve.limit() < 1100000);
+        sieve.extend_to(1000);
+        assert!(sieve.limit() >= 1000000 && sieve.limit() < 1100000);
+
+        // Primes which are already known can be found while the sieve is borrowed.
+        let borrowed = sieve.sieve();
+        assert_eq!(sieve.nth_prime(999), 7919);
+        assert_eq!(borrowed.nth_prime(999), Some(7919));
+    }
+
+    #[test]
+    #[should_panic(expected = "GrowingSieve extended while borrowed")]
+    fn test_growing_extend_while_borrowed() {
+        let sieve = GrowingSieve::new();
+        let _borrowed = sieve.sieve();
+        sieve.extend_to(1000000);
+    }
+}
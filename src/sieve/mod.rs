@@ -1,10 +1,16 @@
 //! A structure which sieves for prime numbers and provides functions to iterate over the primes,
 //! to get the nth prime and for querying whether a particular number is prime.
 
+mod growing;
 mod primefuncs;
 
-use iterator;
-use segsieve::segmented_sieve;
+pub use self::growing::GrowingSieve;
+
+use std::cmp::max;
+
+use bounds::nth_prime_upper_bound;
+use iterator::{self, SMALL_PRIMES};
+use segsieve::{segmented_sieve, segmented_sieve_parallel, segmented_sieve_range, SegmentedSieve};
 
 const MODULUS: u64 = 240;
 
@@ -15,14 +21,14 @@ enum SmallPrime {
     None,
 }
 
-/// A function which calculates an upper bound for the nth prime, using the bounds given on
-/// [Wikipedia](https://en.wikipedia.org/wiki/Prime_number_theorem#Approximations_for_the_nth_prime_number)
-fn upper_bound(n: usize) -> u64 {
-    match n {
-        0...5 => 12,
-        _ => {
-            let f = n as f64;
-            (f * (f.ln() + f.ln().ln())) as u64
+impl SmallPrime {
+    /// The first of the small primes 2, 3 and 5 which is at least the given number.
+    fn first_from(low: u64) -> SmallPrime {
+        match low {
+            0..=2 => SmallPrime::Two,
+            3 => SmallPrime::Three,
+            4..=5 => SmallPrime::Five,
+            _ => SmallPrime::None,
         }
     }
 }
@@ -34,48 +40,148 @@ pub struct Sieve {
     primes: Vec<u64>,
     /// Intermediate counts of the number of primes up to a particular point.
     counts: Vec<usize>,
+    /// The lowest number that this sieve knows about.
+    low: u64,
 }
 
 impl Sieve {
-    /// Create a new `Sieve` which knows about the primes up to the given limit.
-    pub fn to_limit(limit: u64) -> Sieve {
-        // Sieve for primes using a segmented sieve.
-        let sieve = segmented_sieve(limit);
-
+    /// Create a `Sieve` from the encoding of the primes from `low` onwards, as produced by the
+    /// segmented sieve.
+    fn from_encoding(primes: Vec<u64>, low: u64) -> Sieve {
         // Count the number of primes up to intermediate points in the sieve.
-        let mut counts = Vec::with_capacity(sieve.len());
+        let mut counts = Vec::with_capacity(primes.len());
         let mut count = 0;
-        for num in &sieve {
+        for num in &primes {
             count += num.count_ones() as usize;
             counts.push(count);
         }
 
         Sieve {
-            primes: sieve,
-            counts: counts,
+            primes,
+            counts,
+            low,
         }
     }
 
+    /// Create a new `Sieve` which knows about the primes up to the given limit.
+    pub fn to_limit(limit: u64) -> Sieve {
+        Sieve::from_encoding(segmented_sieve(limit), 0)
+    }
+
+    /// Create a new `Sieve` which knows about the primes up to the given limit, splitting the work
+    /// of sieving between the given number of threads.
+    ///
+    /// The resulting `Sieve` is identical to the one produced by `Sieve::to_limit`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let sieve = primesieve::Sieve::to_limit_parallel(100000000, 4);
+    /// assert_eq!(sieve.nth_prime(5761454), Some(99999989));
+    /// ```
+    pub fn to_limit_parallel(limit: u64, threads: usize) -> Sieve {
+        Sieve::from_encoding(segmented_sieve_parallel(limit, threads), 0)
+    }
+
     /// Create a new `Sieve` which knows about at least the first `n` primes.
+    ///
+    /// The sieve extends as far as the upper bound for the last of these primes given by
+    /// `nth_prime_upper_bound`, which is within a fraction of a percent of the prime itself once
+    /// `n` is large, so very little is sieved beyond what is needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let sieve = primesieve::Sieve::to_n_primes(1000000);
+    /// assert_eq!(sieve.nth_prime(999999), Some(15485863));
+    /// assert!(sieve.limit() < 15500000);
+    /// ```
     pub fn to_n_primes(n: usize) -> Sieve {
-        // Get an upper bound on the `n`th prime and sieve for primes up to that limit using a
-        // segmented sieve.
-        let sieve = segmented_sieve(upper_bound(n + 1));
+        // Get an upper bound on the last of the primes and sieve for primes up to that limit
+        // using a segmented sieve.
+        let limit = nth_prime_upper_bound(n.saturating_sub(1) as u64);
+        Sieve::from_encoding(segmented_sieve(limit), 0)
+    }
 
-        // Count the number of primes up to intermediate points in the sieve.
-        let mut counts = Vec::with_capacity(sieve.len());
-        let mut count = 0;
-        for num in &sieve {
-            count += num.count_ones() as usize;
-            counts.push(count);
+    /// Create a new `Sieve` which knows only about the primes in the interval `[lo, hi)`.
+    ///
+    /// Only the primes up to the square root of `hi` are needed to do the sieving, so this can be
+    /// used to find the primes in a short interval far away from zero without sieving everything
+    /// below it. Iteration, counting and indexing are all relative to the start of the interval.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let sieve = primesieve::Sieve::in_range(1000000000000, 1000000000100);
+    ///
+    /// assert_eq!(sieve.lower_limit(), 1000000000000);
+    /// assert_eq!(sieve.iter().take_while(|&x| x < 1000000000100).collect::<Vec<u64>>(),
+    ///            vec![1000000000039, 1000000000061, 1000000000063, 1000000000091]);
+    /// assert_eq!(sieve.nth_prime(0), Some(1000000000039));
+    /// ```
+    pub fn in_range(lo: u64, hi: u64) -> Sieve {
+        Sieve::from_encoding(segmented_sieve_range(lo, hi), lo)
+    }
+
+    /// Extend this `Sieve` in place so that it knows about the primes up to at least `new_limit`,
+    /// doing nothing if it already does.
+    ///
+    /// The sieving resumes from the current `limit()`, with sieving primes found afresh for the
+    /// new limit, so none of the work already done is repeated. A sieve created with
+    /// `Sieve::in_range` keeps its lower limit, and is extended upwards in the same way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut sieve = primesieve::Sieve::to_limit(1000);
+    /// assert_eq!(sieve.nth_prime(999), None);
+    ///
+    /// sieve.extend_to(10000);
+    /// assert!(sieve.limit() >= 10000);
+    /// assert_eq!(sieve.nth_prime(999), Some(7919));
+    /// ```
+    pub fn extend_to(&mut self, new_limit: u64) {
+        let limit = self.limit();
+        if new_limit < limit {
+            return;
         }
 
-        Sieve {
-            primes: sieve,
-            counts: counts,
+        // An empty sieve may end before its lower limit, in which case the new segments must
+        // still start from the lower limit.
+        let mut sieve = SegmentedSieve::new(max(limit, self.low), new_limit.saturating_add(1));
+        let mut count = self.counts.last().cloned().unwrap_or(0);
+        while sieve.advance() {
+            for &word in sieve.segment() {
+                count += word.count_ones() as usize;
+                self.primes.push(word);
+                self.counts.push(count);
+            }
         }
     }
 
+    /// The number of the small primes 2, 3 and 5 which lie inside this `Sieve`.
+    fn num_small_primes(&self) -> usize {
+        SMALL_PRIMES.iter().filter(|&&p| self.low <= p && p < self.limit()).count()
+    }
+
+    /// The number which is represented by the first bit of `self.primes`.
+    fn base(&self) -> u64 {
+        self.low - self.low % MODULUS
+    }
+
+    /// Returns the lowest number that this `Sieve` knows about. This is zero unless the sieve was
+    /// created with `Sieve::in_range`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let sieve = primesieve::Sieve::in_range(1000, 2000);
+    /// assert_eq!(sieve.lower_limit(), 1000);
+    /// ```
+    pub fn lower_limit(&self) -> u64 {
+        self.low
+    }
+
     /// Returns the highest number that this `Sieve` knows about. Note that this may be slightly
     /// larger than the limit the sieve was created with.
     ///
@@ -86,7 +192,7 @@ impl Sieve {
     /// assert!(sieve.limit() >= 1000);
     /// ```
     pub fn limit(&self) -> u64 {
-        MODULUS * self.primes.len() as u64
+        self.base() + MODULUS * self.primes.len() as u64
     }
 
     /// Returns the number of primes that this `Sieve` knows about. Note that this may be slightly
@@ -99,7 +205,7 @@ impl Sieve {
     /// assert!(sieve.num_primes() >= 1000);
     /// ```
     pub fn num_primes(&self) -> usize {
-        self.counts[self.counts.len() - 1]
+        self.num_small_primes() + self.counts.last().cloned().unwrap_or(0)
     }
 
     /// Returns the `n`th prime number, indexed from 0, or `None` if fewer than `n` prime numbers
@@ -123,36 +229,33 @@ impl Sieve {
     /// assert_eq!(sieve.nth_prime(1000), None);
     /// ```
     pub fn nth_prime(&self, n: usize) -> Option<u64> {
-        // If n is small enough (i.e. 0, 1 or 2) then return the prime directly. Otherwise, we
-        // should do a binary search of `self.counts` to find the right prime.
-        match n {
-            0 => Some(2),
-            1 => Some(3),
-            2 => Some(5),
-            _ => {
-                if n < self.num_primes() {
-                    // Find the index into `self.primes` where we will find the `n`th prime,
-                    // remembering that the stored counts are offset by 3.
-                    let k = n - 3;
-                    let idx = match self.counts.binary_search(&k) {
-                        Err(x) => x,
-                        Ok(mut x) => {
-                            while self.counts[x] == k {
-                                x += 1;
-                            }
-                            x
-                        }
-                    };
-
-                    // Now find the specific prime within this chunk of primes.
-                    let count = self.counts[idx] - self.primes[idx].count_ones() as usize;
-                    let primes = &[self.primes[idx]];
-                    let mut primes_iter = iterator::SieveIterator::new(primes);
-                    Some(MODULUS * idx as u64 + primes_iter.nth((k - count) as usize).unwrap())
-                } else {
-                    None
+        // If n is small enough that the answer is one of 2, 3 or 5, then return the prime
+        // directly. Otherwise, we should do a binary search of `self.counts` to find the right
+        // prime.
+        let small = self.num_small_primes();
+        if n < small {
+            Some(SMALL_PRIMES[SMALL_PRIMES.len() - small + n])
+        } else if n < self.num_primes() {
+            // Find the index into `self.primes` where we will find the `n`th prime, remembering
+            // that the stored counts are offset by the number of small primes.
+            let k = n - small;
+            let idx = match self.counts.binary_search(&k) {
+                Err(x) => x,
+                Ok(mut x) => {
+                    while self.counts[x] == k {
+                        x += 1;
+                    }
+                    x
                 }
-            }
+            };
+
+            // Now find the specific prime within this chunk of primes.
+            let count = self.counts[idx] - self.primes[idx].count_ones() as usize;
+            let primes = &[self.primes[idx]];
+            let mut primes_iter = iterator::SieveIterator::new(primes);
+            Some(self.base() + MODULUS * idx as u64 + primes_iter.nth(k - count).unwrap())
+        } else {
+            None
         }
     }
 }
@@ -169,9 +272,16 @@ impl<'a> Sieve {
     ///                 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97]);
     /// ```
     pub fn iter(&'a self) -> SieveIterator<'a> {
+        // A sieve over an empty interval holds no primes at all, not even the small ones.
+        let small = if self.primes.is_empty() {
+            SmallPrime::None
+        } else {
+            SmallPrime::first_from(self.low)
+        };
+
         SieveIterator {
-            small: SmallPrime::Two,
-            sieve_iter: iterator::SieveIterator::new(&self.primes),
+            small,
+            sieve_iter: iterator::SieveIterator::with_offset(&self.primes, self.base()),
         }
     }
 }
@@ -1,94 +1,132 @@
 //! Functions, such as factorisation and similar computations, which require use of prime numbers
 //! to be calculated.
 
+use arith::divisor_power_sum;
+use divisors::DivisorIterator;
+use error::Error;
+use factor;
+use factorisation::Factorisation;
+use multiplicative::Multiplicative;
+use primality::{baillie_psw, miller_rabin};
 use segment;
 use sieve::Sieve;
 
-impl Sieve {
-    /// Uses trial division to determine if the given number is prime.
-    fn trial_division(&self, n: u64) -> bool {
-        for p in self.iter() {
-            if p.saturating_mul(p) > n {
-                return true;
-            } else if n % p == 0 {
-                return false;
-            }
-        }
+/// The largest prime used for trial division before applying the Baillie-PSW test.
+const TRIAL_DIVISION_LIMIT: u64 = 1000;
 
-        true
-    }
-
-    /// Returns whether or not `n` is a prime number, or `Err(())` if `n` is larger than the square
-    /// of the largest prime held in the sieve.
+impl Sieve {
+    /// Returns whether or not `n` is a prime number.
     ///
-    /// Uses a simple lookup if `n` is not greater than the largest number known about by the
-    /// sieve, and uses trial division otherwise.
+    /// Uses a simple lookup if `n` is within the range of numbers known about by the sieve, and
+    /// uses a deterministic Miller-Rabin test otherwise, so the answer is correct for every `u64`
+    /// no matter how small the sieve.
     ///
     /// # Examples
     ///
     /// ```
     /// let sieve = primesieve::Sieve::to_limit(100);
     ///
-    /// assert_eq!(sieve.is_prime(0), Ok(false));
-    /// assert_eq!(sieve.is_prime(1), Ok(false));
-    /// assert_eq!(sieve.is_prime(2), Ok(true));
-    /// assert_eq!(sieve.is_prime(3), Ok(true));
-    /// assert_eq!(sieve.is_prime(4), Ok(false));
-    /// assert_eq!(sieve.is_prime(5), Ok(true));
+    /// assert!(!sieve.is_prime(0));
+    /// assert!(!sieve.is_prime(1));
+    /// assert!(sieve.is_prime(2));
+    /// assert!(sieve.is_prime(3));
+    /// assert!(!sieve.is_prime(4));
+    /// assert!(sieve.is_prime(5));
     ///
-    /// assert_eq!(sieve.is_prime(491), Ok(true));
-    /// assert_eq!(sieve.is_prime(493), Ok(false));
-    /// assert_eq!(sieve.is_prime(495), Ok(false));
-    /// assert_eq!(sieve.is_prime(497), Ok(false));
-    /// assert_eq!(sieve.is_prime(499), Ok(true));
+    /// assert!(sieve.is_prime(491));
+    /// assert!(!sieve.is_prime(493));
+    /// assert!(!sieve.is_prime(495));
+    /// assert!(!sieve.is_prime(497));
+    /// assert!(sieve.is_prime(499));
     ///
-    /// assert_eq!(sieve.is_prime(1000001), Err(()));
+    /// assert!(!sieve.is_prime(1000001));
+    /// assert!(sieve.is_prime(18446744073709551557));
     /// ```
-    pub fn is_prime(&self, n: u64) -> Result<bool, ()> {
+    pub fn is_prime(&self, n: u64) -> bool {
         match n {
-            2 | 3 | 5 => Ok(true),
+            2 | 3 | 5 => true,
             _ => {
-                if n < self.limit() {
-                    Ok(segment::get(&self.primes, n))
-                } else if n <= self.limit().saturating_mul(self.limit()) {
-                    Ok(Sieve::trial_division(self, n))
+                if self.low <= n && n < self.limit() {
+                    segment::get(&self.primes, n - self.base())
                 } else {
-                    Err(())
+                    miller_rabin(n)
                 }
             }
         }
     }
 
-    /// Factorises `n` into (prime, exponent) pairs.
+    /// Returns whether or not `n` is a prime number, for `n` which may not fit in a `u64`.
+    ///
+    /// Numbers which fit in a `u64` are handled as in `is_prime`. Larger numbers are first checked
+    /// for divisibility by the small primes held in the sieve, and then tested using the
+    /// Baillie-PSW test from the `primality` module.
     ///
-    /// Returns `Err(remainder, partial factorisation)` if `n` cannot be fully factorised without
-    /// sieving for more primes.
+    /// # Examples
     ///
-    /// If `x` is the largest number known about by the sieve, then any integer having at most one
-    /// prime factor larger than `x` can be factorised. In particular, any number not greater than
-    /// `x^2` can be factorised.
+    /// ```
+    /// let sieve = primesieve::Sieve::to_limit(100);
+    ///
+    /// assert!(sieve.is_prime_u128(97));
+    /// assert!(sieve.is_prime_u128(170141183460469231731687303715884105727));
+    /// assert!(!sieve.is_prime_u128(18446744073709551557 * 9223372036854775837));
+    /// ```
+    pub fn is_prime_u128(&self, n: u128) -> bool {
+        if n <= u64::MAX as u128 {
+            return self.is_prime(n as u64);
+        }
+
+        if self.low == 0 {
+            for p in self.iter().take_while(|&p| p <= TRIAL_DIVISION_LIMIT) {
+                if n % p as u128 == 0 {
+                    return false;
+                }
+            }
+        }
+
+        baillie_psw(n)
+    }
+
+    /// Factorises `n` into prime powers.
+    ///
+    /// Returns `Err(Error::Zero)` if `n` is 0, and a complete factorisation otherwise.
+    ///
+    /// Trial division by the primes held in the sieve is used first. If this does not finish the
+    /// job, then whatever is left over is factorised using a deterministic Miller-Rabin test and
+    /// Brent's variant of Pollard's rho algorithm, so that even a small sieve can factorise any
+    /// `u64`.
     ///
     /// # Examples
     ///
     /// ```
+    /// use primesieve::Error;
+    ///
     /// let sieve = primesieve::Sieve::to_limit(100);
+    /// let factorise = |n| sieve.factorise(n).map(|factors| factors.to_string());
     ///
-    /// assert_eq!(sieve.factorise(2), Ok(vec![(2, 1)]));
-    /// assert_eq!(sieve.factorise(4), Ok(vec![(2, 2)]));
-    /// assert_eq!(sieve.factorise(1 << 63), Ok(vec![(2, 63)]));
+    /// assert_eq!(factorise(1), Ok("1".to_string()));
+    /// assert_eq!(factorise(2), Ok("2".to_string()));
+    /// assert_eq!(factorise(4), Ok("2^2".to_string()));
+    /// assert_eq!(factorise(1 << 63), Ok("2^63".to_string()));
     ///
-    /// assert_eq!(sieve.factorise(2 * 3), Ok(vec![(2, 1), (3, 1)]));
-    /// assert_eq!(sieve.factorise(89 * 97), Ok(vec![(89, 1), (97, 1)]));
-    /// assert_eq!(sieve.factorise(8 * 9 * 5), Ok(vec![(2, 3), (3, 2), (5, 1)]));
+    /// assert_eq!(factorise(2 * 3), Ok("2 * 3".to_string()));
+    /// assert_eq!(factorise(89 * 97), Ok("89 * 97".to_string()));
+    /// assert_eq!(factorise(8 * 9 * 5), Ok("2^3 * 3^2 * 5".to_string()));
     ///
-    /// assert_eq!(sieve.factorise(2 * 3 * 5 * 991), Ok(vec![(2, 1), (3, 1), (5, 1), (991, 1)]));
-    /// assert_eq!(sieve.factorise(2 * 3 * 5 * 991 * 991),
-    ///            Err((991 * 991, vec![(2, 1), (3, 1), (5, 1)])));
+    /// assert_eq!(factorise(2 * 3 * 5 * 991), Ok("2 * 3 * 5 * 991".to_string()));
+    /// assert_eq!(factorise(2 * 3 * 5 * 991 * 991), Ok("2 * 3 * 5 * 991^2".to_string()));
+    /// assert_eq!(factorise(4294967291 * 4294967279), Ok("4294967279 * 4294967291".to_string()));
+    ///
+    /// assert_eq!(sieve.factorise(360).unwrap().factors(), &[(2, 3), (3, 2), (5, 1)]);
+    /// assert_eq!(sieve.factorise(0), Err(Error::Zero));
     /// ```
-    pub fn factorise(&self, mut n: u64) -> Result<Vec<(u64, u64)>, (u64, Vec<(u64, u64)>)> {
+    pub fn factorise(&self, mut n: u64) -> Result<Factorisation, Error> {
         // Deal with small values of `n` as special cases.
-        if n == 0 { return Err((0, vec![])) }
-        if n == 1 { return Ok(vec![]) }
+        if n == 0 { return Err(Error::Zero) }
+        if n == 1 { return Ok(Factorisation::one()) }
+
+        // A sieve created with `Sieve::in_range` does not know about the small primes, so leave
+        // everything to Pollard's rho algorithm.
+        if self.low > 0 { return Ok(factor::factorise_from(n, 2).into_iter().collect()) }
 
         // Somewhere to store the result.
         let mut factors = Vec::new();
@@ -113,10 +151,84 @@ impl Sieve {
         }
 
         // If there are any leftovers, check if it is small enough that we can guarantee that it
-        // is prime.
+        // is prime, and otherwise factorise it using Pollard's rho algorithm.
         if n != 1 {
             if self.limit().saturating_mul(self.limit()) < n {
-                return Err((n, factors));
+                factors.extend(factor::factorise_from(n, self.limit()));
+            } else {
+                factors.push((n, 1));
+            }
+        }
+
+        Ok(factors.into_iter().collect())
+    }
+
+    /// Factorises `n`, which may not fit in a `u64`, into (prime, exponent) pairs.
+    ///
+    /// Returns `Err(Error::Zero)` if `n` is 0, and a complete factorisation otherwise. The result
+    /// is given as (prime, exponent) pairs in increasing order of the primes rather than as a
+    /// `Factorisation`, because a `Factorisation` holds its primes as `u64` and the primes dividing
+    /// a `u128` may not fit in one.
+    ///
+    /// Numbers which fit in a `u64` are handled as in `factorise`. For larger numbers, trial
+    /// division by the primes held in the sieve is used first, and then whatever is left over is
+    /// split using SQUFOF if it is below 80 bits, and Lenstra's elliptic curve method otherwise.
+    /// The primes needed by the elliptic curve method are taken from this sieve if it holds
+    /// enough of them, and sieved separately otherwise.
+    ///
+    /// The smallest prime factor of a composite `u128` has at most 20 digits, and factors of this
+    /// size are found quickly, so `Err(Error::FactorNotFound { .. })` is only returned in the
+    /// vanishingly unlikely event that every elliptic curve tried fails to find one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let sieve = primesieve::Sieve::to_limit(100);
+    ///
+    /// assert_eq!(sieve.factorise_u128(1 << 100), Ok(vec![(2, 100)]));
+    /// assert_eq!(sieve.factorise_u128(1180591620717411303449 * 1125899906842679 * 3),
+    ///            Ok(vec![(3, 1), (1125899906842679, 1), (1180591620717411303449, 1)]));
+    /// ```
+    pub fn factorise_u128(&self, mut n: u128) -> Result<Vec<(u128, u64)>, Error> {
+        if n <= u64::MAX as u128 {
+            let factors = self.factorise(n as u64)?;
+            return Ok(factors.into_iter().map(|(p, k)| (p as u128, k)).collect());
+        }
+
+        // The primes used by ECM, taken from this sieve if possible.
+        let ecm_primes = || {
+            if self.low == 0 && self.limit() > factor::ECM_PRIME_LIMIT {
+                self.iter().take_while(|&p| p <= factor::ECM_PRIME_LIMIT).collect()
+            } else {
+                let sieve = Sieve::to_limit(factor::ECM_PRIME_LIMIT);
+                sieve.iter().take_while(|&p| p <= factor::ECM_PRIME_LIMIT).collect()
+            }
+        };
+
+        // A sieve created with `Sieve::in_range` does not know about the small primes.
+        if self.low > 0 { return factor::factorise_u128_from(n, 2, ecm_primes) }
+
+        // Trial division by the primes held in the sieve.
+        let mut factors = Vec::new();
+        for p in self.iter() {
+            if (p as u128) * (p as u128) > n {
+                break;
+            }
+
+            let mut count = 0;
+            while n % p as u128 == 0 {
+                n /= p as u128;
+                count += 1;
+            }
+            if count > 0 {
+                factors.push((p as u128, count));
+            }
+        }
+
+        // Deal with the leftovers as in `factorise`.
+        if n != 1 {
+            if (self.limit() as u128) * (self.limit() as u128) < n {
+                factors.extend(factor::factorise_u128_from(n, self.limit(), ecm_primes)?);
             } else {
                 factors.push((n, 1));
             }
@@ -130,7 +242,7 @@ impl Sieve {
     /// Uses the formula based on the factorisation of `n`, that is `ϕ(n)` is equal to `n` times
     /// the product of `1 - 1/p`, where `p` ranges over the distinct prime factors of `n`.
     ///
-    /// Returns `Err(())` if `n` cannot be factorised without first sieving for more primes.
+    /// Returns `Err(Error::Zero)` if `n` is 0.
     ///
     /// # Examples
     ///
@@ -146,22 +258,19 @@ impl Sieve {
     /// assert_eq!(sieve.euler_phi(8 * 9 * 5), Ok(4 * 6 * 4));
     ///
     /// assert_eq!(sieve.euler_phi(2 * 3 * 5 * 991), Ok(2 * 4 * 990));
-    /// assert_eq!(sieve.euler_phi(2 * 3 * 5 * 991 * 991), Err(()));
+    /// assert_eq!(sieve.euler_phi(2 * 3 * 5 * 991 * 991), Ok(2 * 4 * 990 * 991));
+    /// assert_eq!(sieve.euler_phi(0), Err(primesieve::Error::Zero));
     /// ```
-    pub fn euler_phi(&self, mut n: u64) -> Result<u64, ()> {
-        if let Ok(factors) = self.factorise(n) {
-            for (p, _) in factors {
-                n = (n / p) * (p - 1);
-            }
-            Ok(n)
-        } else {
-            Err(())
+    pub fn euler_phi(&self, mut n: u64) -> Result<u64, Error> {
+        for (p, _) in self.factorise(n)? {
+            n = (n / p) * (p - 1);
         }
+        Ok(n)
     }
 
     /// Calculates the number of divisors of `n`.
     ///
-    /// Returns Err(()) is `n` cannot be fully factorised without first sieving for more primes.
+    /// Returns `Err(Error::Zero)` if `n` is 0.
     ///
     /// This uses the well-known formula, that if `n` is given in factorised form as a product
     /// `p_i ^ a_i`, then the number of divisors of `n` is given by:
@@ -182,13 +291,183 @@ impl Sieve {
     /// assert_eq!(sieve.number_of_divisors(8 * 9 * 5), Ok(4 * 3 * 2));
     ///
     /// assert_eq!(sieve.number_of_divisors(2 * 3 * 5 * 991), Ok(2 * 2 * 2 * 2));
-    /// assert_eq!(sieve.number_of_divisors(2 * 3 * 5 * 991 * 991), Err(()));
+    /// assert_eq!(sieve.number_of_divisors(2 * 3 * 5 * 991 * 991), Ok(2 * 2 * 2 * 3));
+    /// assert_eq!(sieve.number_of_divisors(0), Err(primesieve::Error::Zero));
+    /// ```
+    pub fn number_of_divisors(&self, n: u64) -> Result<u64, Error> {
+        Ok(self.factorise(n)?.iter().map(|x| x.1 + 1).product())
+    }
+
+    /// Returns a lazy iterator over the divisors of `n`, which are not yielded in any particular
+    /// order, or `Err(Error::Zero)` if `n` is 0.
+    ///
+    /// # Examples
+    ///
     /// ```
-    pub fn number_of_divisors(&self, n: u64) -> Result<u64, ()> {
-        if let Ok(factors) = self.factorise(n) {
-            Ok(factors.iter().map(|x| x.1 + 1).product())
-        } else {
-            Err(())
+    /// let sieve = primesieve::Sieve::to_limit(100);
+    ///
+    /// assert_eq!(sieve.divisor_iter(12).unwrap().count(), 6);
+    /// assert_eq!(sieve.divisor_iter(1 << 40).unwrap().max(), Some(1 << 40));
+    /// ```
+    pub fn divisor_iter(&self, n: u64) -> Result<DivisorIterator, Error> {
+        Ok(self.factorise(n)?.divisors())
+    }
+
+    /// Returns the divisors of `n` in increasing order, or `Err(Error::Zero)` if `n` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let sieve = primesieve::Sieve::to_limit(100);
+    ///
+    /// assert_eq!(sieve.divisors(1), Ok(vec![1]));
+    /// assert_eq!(sieve.divisors(12), Ok(vec![1, 2, 3, 4, 6, 12]));
+    /// assert_eq!(sieve.divisors(97), Ok(vec![1, 97]));
+    /// assert_eq!(sieve.divisors(0), Err(primesieve::Error::Zero));
+    /// ```
+    pub fn divisors(&self, n: u64) -> Result<Vec<u64>, Error> {
+        let mut divisors = self.divisor_iter(n)?.collect::<Vec<u64>>();
+        divisors.sort_unstable();
+        Ok(divisors)
+    }
+
+    /// Returns the unitary divisors of `n` in increasing order, that is the divisors `d` for
+    /// which `d` and `n / d` are coprime, or `Err(Error::Zero)` if `n` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let sieve = primesieve::Sieve::to_limit(100);
+    ///
+    /// assert_eq!(sieve.unitary_divisors(12), Ok(vec![1, 3, 4, 12]));
+    /// ```
+    pub fn unitary_divisors(&self, n: u64) -> Result<Vec<u64>, Error> {
+        let factors = self.factorise(n)?;
+        let mut divisors = DivisorIterator::unitary(factors.factors()).collect::<Vec<u64>>();
+        divisors.sort_unstable();
+        Ok(divisors)
+    }
+
+    /// Returns the squarefree divisors of `n` in increasing order, or `Err(Error::Zero)` if `n`
+    /// is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let sieve = primesieve::Sieve::to_limit(100);
+    ///
+    /// assert_eq!(sieve.squarefree_divisors(12), Ok(vec![1, 2, 3, 6]));
+    /// ```
+    pub fn squarefree_divisors(&self, n: u64) -> Result<Vec<u64>, Error> {
+        let factors = self.factorise(n)?;
+        let mut divisors = DivisorIterator::squarefree(factors.factors()).collect::<Vec<u64>>();
+        divisors.sort_unstable();
+        Ok(divisors)
+    }
+
+    /// Calculates the sum `σ_k(n)` of the `k`th powers of the divisors of `n`, or returns
+    /// `Err(Error::Zero)` if `n` is 0.
+    ///
+    /// This uses the factorisation of `n` rather than listing the divisors, since `σ_k` is
+    /// multiplicative, with `σ_k(p^e) = 1 + p^k + p^2k + ... + p^ek`. The result may overflow for
+    /// large `k`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let sieve = primesieve::Sieve::to_limit(100);
+    ///
+    /// assert_eq!(sieve.sigma(12, 0), Ok(6));
+    /// assert_eq!(sieve.sigma(12, 1), Ok(28));
+    /// assert_eq!(sieve.sigma(12, 2), Ok(1 + 4 + 9 + 16 + 36 + 144));
+    /// assert_eq!(sieve.sigma(0, 1), Err(primesieve::Error::Zero));
+    /// ```
+    pub fn sigma(&self, n: u64, k: u32) -> Result<u128, Error> {
+        Ok(self.factorise(n)?.into_iter().map(|(p, e)| divisor_power_sum(p, k, e)).product())
+    }
+
+    /// Evaluates the multiplicative function `F` at `n`, using the factorisation of `n`.
+    ///
+    /// Returns `Err(Error::Zero)` if `n` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use primesieve::multiplicative::{Jordan, Liouville, Radical};
+    ///
+    /// let sieve = primesieve::Sieve::to_limit(100);
+    ///
+    /// assert_eq!(sieve.evaluate::<Radical>(8 * 9 * 5), Ok(30));
+    /// assert_eq!(sieve.evaluate::<Liouville>(8 * 9 * 5), Ok(1));
+    /// assert_eq!(sieve.evaluate::<Jordan<2>>(6), Ok(24));
+    /// assert_eq!(sieve.evaluate::<Radical>(0), Err(primesieve::Error::Zero));
+    /// ```
+    pub fn evaluate<F: Multiplicative>(&self, n: u64) -> Result<F::Value, Error> {
+        let value = self.factorise(n)?.into_iter().fold(F::Value::from(1), |value, (p, k)| {
+            value * F::prime_power(p, k)
+        });
+        Ok(value)
+    }
+
+    /// Calculates the values of the multiplicative function `F` at every number up to the given
+    /// limit, using a linear sieve.
+    ///
+    /// The value at index `n` of the result is the value of `F` at `n`, with a value of 0 at index
+    /// 0. Each composite number is visited exactly once, as the product of its least prime factor
+    /// with a smaller number, so the table is built in linear time with a single evaluation of `F`
+    /// at a prime power for each number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use primesieve::Sieve;
+    /// use primesieve::multiplicative::{EulerPhi, Mobius};
+    ///
+    /// assert_eq!(Sieve::tabulate::<EulerPhi>(10), vec![0, 1, 1, 2, 2, 4, 2, 6, 4, 6, 4]);
+    /// assert_eq!(Sieve::tabulate::<Mobius>(10), vec![0, 1, -1, -1, 0, -1, 1, -1, 0, 0, 1]);
+    /// ```
+    pub fn tabulate<F: Multiplicative>(limit: u64) -> Vec<F::Value> {
+        let limit = limit as usize;
+        let mut values = vec![F::Value::from(0); limit + 1];
+        if limit == 0 {
+            return values;
         }
+        values[1] = F::Value::from(1);
+
+        // For each number `n`, keep the exponent of its least prime factor `p`, along with the part
+        // of `n` which is coprime to `p`. An exponent of 0 marks a number not yet visited, which
+        // must therefore be prime.
+        let mut exponents = vec![0u8; limit + 1];
+        let mut cofactors = vec![0usize; limit + 1];
+        let mut primes: Vec<usize> = Vec::new();
+
+        for n in 2..=limit {
+            if exponents[n] == 0 {
+                exponents[n] = 1;
+                cofactors[n] = 1;
+                values[n] = F::prime_power(n as u64, 1);
+                primes.push(n);
+            }
+
+            // Visit `pn` for every prime `p` up to the least prime factor of `n`.
+            for &p in &primes {
+                let multiple = p * n;
+                if multiple > limit {
+                    break;
+                }
+                if n % p == 0 {
+                    exponents[multiple] = exponents[n] + 1;
+                    cofactors[multiple] = cofactors[n];
+                    let power = F::prime_power(p as u64, exponents[multiple] as u64);
+                    values[multiple] = values[cofactors[n]] * power;
+                    break;
+                }
+                exponents[multiple] = 1;
+                cofactors[multiple] = n;
+                values[multiple] = values[n] * F::prime_power(p as u64, 1);
+            }
+        }
+
+        values
     }
 }
@@ -0,0 +1,154 @@
+//! A table of least prime factors, for quickly factorising many small numbers.
+
+use error::Error;
+use factorisation::Factorisation;
+use segsieve::sieving_primes;
+use wheel::Wheel30;
+
+/// The position within each block of 30 of the numbers coprime to 30, or `NONE` for the others.
+const NONE: u8 = u8::MAX;
+const WHEEL_INDEX: &[u8; 30] = &[
+    NONE, 0, NONE, NONE, NONE, NONE, NONE, 1, NONE, NONE, NONE, 2, NONE, 3, NONE,
+    NONE, NONE, 4, NONE, 5, NONE, NONE, NONE, 6, NONE, NONE, NONE, NONE, NONE, 7,
+];
+
+/// The index into the table at which the entry for `n` is found, if `n` is coprime to 30.
+#[inline]
+fn index_for(n: u64) -> Option<usize> {
+    match WHEEL_INDEX[(n % 30) as usize] {
+        NONE => None,
+        pos => Some(8 * (n / 30) as usize + pos as usize),
+    }
+}
+
+/// A table of the least prime factor of every number up to a given limit, which allows numbers to
+/// be factorised by repeated lookups rather than trial division.
+///
+/// To save memory, entries are only stored for numbers which are coprime to 30, since factors of
+/// 2, 3 and 5 are easily removed by division. Entries are stored as `u32`, with 0 for primes, and
+/// so the table takes a little over one byte per number up to the limit.
+pub struct SpfSieve {
+    /// The least prime factor of each number coprime to 30, or 0 for primes.
+    spf: Vec<u32>,
+    /// The largest number held in the table.
+    limit: u64,
+}
+
+impl SpfSieve {
+    /// Create a new `SpfSieve` which knows the least prime factors of the numbers up to the given
+    /// limit.
+    pub fn to_limit(limit: u64) -> SpfSieve {
+        let mut spf = vec![0; 8 * (limit / 30 + 1) as usize];
+
+        // Cross off the multiples of each prime, starting at its square and using a wheel to skip
+        // multiples of 2, 3 and 5. The primes are taken in increasing order, so the first one to
+        // reach each entry is its least prime factor.
+        for prime in sieving_primes(limit) {
+            if prime * prime > limit {
+                break;
+            }
+            let mut wheel = Wheel30::new(prime, prime);
+            let mut multiple = prime * prime;
+            while multiple <= limit {
+                let idx = index_for(multiple).unwrap();
+                if spf[idx] == 0 {
+                    spf[idx] = prime as u32;
+                }
+                multiple += wheel.next_diff();
+            }
+        }
+
+        SpfSieve { spf, limit }
+    }
+
+    /// Returns the largest number whose least prime factor is known.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Returns the least prime factor of `n`, or `None` if `n` is 0, 1 or larger than the limit of
+    /// this table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let sieve = primesieve::SpfSieve::to_limit(1000);
+    ///
+    /// assert_eq!(sieve.least_prime_factor(1), None);
+    /// assert_eq!(sieve.least_prime_factor(91), Some(7));
+    /// assert_eq!(sieve.least_prime_factor(97), Some(97));
+    /// assert_eq!(sieve.least_prime_factor(998), Some(2));
+    /// assert_eq!(sieve.least_prime_factor(1001), None);
+    /// ```
+    pub fn least_prime_factor(&self, n: u64) -> Option<u64> {
+        if n < 2 || n > self.limit {
+            return None;
+        }
+        for &p in &[2, 3, 5] {
+            if n % p == 0 {
+                return Some(p);
+            }
+        }
+        match self.spf[index_for(n).unwrap()] {
+            0 => Some(n),
+            p => Some(p as u64),
+        }
+    }
+
+    /// Factorises `n` into prime powers, by repeatedly looking up its least prime factor.
+    ///
+    /// Returns `Err(Error::Zero)` if `n` is 0, and `Err(Error::OutOfRange { .. })` if `n` is
+    /// larger than the limit of this table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use primesieve::Error;
+    ///
+    /// let sieve = primesieve::SpfSieve::to_limit(100000);
+    /// let factorise = |n| sieve.factorise(n).map(|factors| factors.to_string());
+    ///
+    /// assert_eq!(factorise(1), Ok("1".to_string()));
+    /// assert_eq!(factorise(8 * 9 * 5), Ok("2^3 * 3^2 * 5".to_string()));
+    /// assert_eq!(factorise(7 * 7 * 7 * 13 * 17), Ok("7^3 * 13 * 17".to_string()));
+    /// assert_eq!(factorise(99991), Ok("99991".to_string()));
+    ///
+    /// assert_eq!(sieve.factorise(0), Err(Error::Zero));
+    /// assert_eq!(sieve.factorise(100001), Err(Error::OutOfRange { needed_limit: 100001 }));
+    /// ```
+    pub fn factorise(&self, mut n: u64) -> Result<Factorisation, Error> {
+        if n == 0 {
+            return Err(Error::Zero);
+        }
+        if n > self.limit {
+            return Err(Error::OutOfRange { needed_limit: n });
+        }
+
+        let mut factors: Vec<(u64, u64)> = Vec::new();
+        while let Some(p) = self.least_prime_factor(n) {
+            n /= p;
+            match factors.last_mut() {
+                Some(&mut (q, ref mut count)) if q == p => *count += 1,
+                _ => factors.push((p, 1)),
+            }
+        }
+
+        Ok(factors.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sieve::Sieve;
+
+    #[test]
+    fn test_matches_sieve() {
+        let limit = 200000;
+        let spf_sieve = SpfSieve::to_limit(limit);
+        let sieve = Sieve::to_limit(1000);
+        for n in 1..=limit {
+            assert_eq!(spf_sieve.factorise(n), sieve.factorise(n));
+        }
+    }
+}
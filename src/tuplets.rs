@@ -0,0 +1,304 @@
+//! Prime k-tuplets, such as twin primes, prime triplets and prime quadruplets.
+//!
+//! A pattern is given by the offsets of its members from the smallest one, so that twin primes
+//! have the pattern `[0, 2]` and prime quadruplets the pattern `[0, 2, 6, 8]`. The tuplets are
+//! found a whole `u64` of the sieve at a time: since every prime beyond 5 is coprime to 30, the
+//! bit for `p + d` lies a fixed distance after the bit for `p` once the residue of `p` modulo 30
+//! is known, so shifting each word of the sieve by that distance and taking the bitwise AND of
+//! the results leaves exactly the bits of those `p` which start a tuplet. The few tuplets which
+//! contain 2, 3 or 5 are checked separately.
+//!
+//! Constants are provided for the densest admissible patterns of up to eight primes, with each
+//! mirror image of an asymmetric pattern given separately.
+//!
+//! # Examples
+//!
+//! ```
+//! use primesieve::tuplets::{self, Tuplets};
+//!
+//! let twins = Tuplets::new(tuplets::TWINS, 0, 100).collect::<Vec<u64>>();
+//! assert_eq!(twins, vec![3, 5, 11, 17, 29, 41, 59, 71]);
+//!
+//! let quadruplets = Tuplets::new(tuplets::QUADRUPLETS, 0, 1000).collect::<Vec<u64>>();
+//! assert_eq!(quadruplets, vec![5, 11, 101, 191, 821]);
+//!
+//! assert_eq!(tuplets::count_tuplets(tuplets::TWINS, 0, 100000000), 440312);
+//! ```
+
+use std::mem;
+
+use iterator::{MODULUS, OFFSETS, SMALL_PRIMES};
+use primality::miller_rabin;
+use segsieve::SegmentedSieve;
+
+/// Twin primes, `(p, p + 2)`.
+pub const TWINS: &[u64] = &[0, 2];
+/// Cousin primes, `(p, p + 4)`.
+pub const COUSINS: &[u64] = &[0, 4];
+/// Sexy primes, `(p, p + 6)`.
+pub const SEXY: &[u64] = &[0, 6];
+/// Prime triplets of the form `(p, p + 2, p + 6)`.
+pub const TRIPLETS_1: &[u64] = &[0, 2, 6];
+/// Prime triplets of the form `(p, p + 4, p + 6)`.
+pub const TRIPLETS_2: &[u64] = &[0, 4, 6];
+/// Prime quadruplets, `(p, p + 2, p + 6, p + 8)`.
+pub const QUADRUPLETS: &[u64] = &[0, 2, 6, 8];
+/// Prime quintuplets of the form `(p, p + 2, p + 6, p + 8, p + 12)`.
+pub const QUINTUPLETS_1: &[u64] = &[0, 2, 6, 8, 12];
+/// Prime quintuplets of the form `(p, p + 4, p + 6, p + 10, p + 12)`.
+pub const QUINTUPLETS_2: &[u64] = &[0, 4, 6, 10, 12];
+/// Prime sextuplets, `(p, p + 4, p + 6, p + 10, p + 12, p + 16)`.
+pub const SEXTUPLETS: &[u64] = &[0, 4, 6, 10, 12, 16];
+/// Prime septuplets of the form `(p, p + 2, p + 6, p + 8, p + 12, p + 18, p + 20)`.
+pub const SEPTUPLETS_1: &[u64] = &[0, 2, 6, 8, 12, 18, 20];
+/// Prime septuplets of the form `(p, p + 2, p + 8, p + 12, p + 14, p + 18, p + 20)`.
+pub const SEPTUPLETS_2: &[u64] = &[0, 2, 8, 12, 14, 18, 20];
+/// Prime octuplets of the form `(p, p + 2, p + 6, p + 8, p + 12, p + 18, p + 20, p + 26)`.
+pub const OCTUPLETS_1: &[u64] = &[0, 2, 6, 8, 12, 18, 20, 26];
+/// Prime octuplets of the form `(p, p + 2, p + 6, p + 12, p + 14, p + 20, p + 24, p + 26)`.
+pub const OCTUPLETS_2: &[u64] = &[0, 2, 6, 12, 14, 20, 24, 26];
+/// Prime octuplets of the form `(p, p + 6, p + 8, p + 14, p + 18, p + 20, p + 24, p + 26)`.
+pub const OCTUPLETS_3: &[u64] = &[0, 6, 8, 14, 18, 20, 24, 26];
+
+/// The numbers coprime to 30 in each block of 30, in the order of their bits in the sieve.
+const RESIDUES: &[u64; 8] = &[1, 7, 11, 13, 17, 19, 23, 29];
+
+/// The bits in a `u64` of the sieve which represent numbers congruent to `RESIDUES[0]` modulo 30.
+const RESIDUE_MASK: u64 = 0x0101010101010101;
+
+/// Returns the position of the bit for `r` within each byte of the sieve, if `r` is coprime to 30.
+fn residue_position(r: u64) -> Option<u64> {
+    RESIDUES.iter().position(|&s| s == r).map(|pos| pos as u64)
+}
+
+/// For each residue modulo 30 at which the given pattern could start, the mask of bits in a `u64`
+/// of the sieve representing that residue, along with the distances from the bit for `p` to the
+/// bits for the other members of the tuplet. Residues with the same distances are merged.
+fn residue_shifts(pattern: &[u64]) -> Vec<(u64, Vec<u64>)> {
+    let mut shifts: Vec<(u64, Vec<u64>)> = Vec::new();
+    'residues: for (pos, &r) in RESIDUES.iter().enumerate() {
+        let mut distances = Vec::with_capacity(pattern.len() - 1);
+        for &d in &pattern[1..] {
+            let n = r + d;
+            match residue_position(n % 30) {
+                Some(bit) => distances.push(8 * (n / 30) + bit - pos as u64),
+                None => continue 'residues,
+            }
+        }
+        match shifts.iter_mut().find(|entry| entry.1 == distances) {
+            Some(entry) => entry.0 |= RESIDUE_MASK << pos,
+            None => shifts.push((RESIDUE_MASK << pos, distances)),
+        }
+    }
+    shifts
+}
+
+/// Returns the bits of `words[idx..]`, shifted down by `shift` places, with missing words taken to
+/// be 0.
+#[inline]
+fn shifted(words: &[u64], idx: usize, shift: u64) -> u64 {
+    let word = |ix: usize| words.get(ix).cloned().unwrap_or(0);
+    let (ix, bits) = (idx + (shift / 64) as usize, shift % 64);
+    match bits {
+        0 => word(ix),
+        _ => (word(ix) >> bits) | (word(ix + 1) << (64 - bits)),
+    }
+}
+
+/// An iterator over the smallest members of the prime k-tuplets with a given pattern, whose
+/// smallest member lies in an interval.
+///
+/// The primes are found with a segmented sieve, which is run a little beyond the end of the
+/// interval so that the larger members of tuplets near the end can be checked.
+///
+/// # Examples
+///
+/// ```
+/// use primesieve::tuplets::{Tuplets, SEXTUPLETS};
+///
+/// let sextuplets = Tuplets::new(SEXTUPLETS, 1000, 100000).collect::<Vec<u64>>();
+/// assert_eq!(sextuplets, vec![16057, 19417, 43777]);
+/// ```
+pub struct Tuplets {
+    /// The tuplets starting at 2, 3 or 5, which are found without the sieve.
+    small: Vec<u64>,
+    /// The sieve which finds the primes in each successive segment.
+    sieve: SegmentedSieve,
+    /// The masks and bit distances from `residue_shifts` for the pattern.
+    shifts: Vec<(u64, Vec<u64>)>,
+    /// The number of words after each word of the sieve which may hold larger members of tuplets
+    /// starting in that word.
+    lookahead: usize,
+    /// The words of the most recently sieved segments, which are kept until they are no longer
+    /// needed to search the words before them.
+    words: Vec<u64>,
+    /// The index in `words` of the next word to search.
+    idx: usize,
+    /// The number represented by the first bit of the next word to search.
+    words_low: u64,
+    /// Whether the sieve has been run to the end.
+    finished: bool,
+    /// The end of the interval.
+    high: u64,
+    /// The bits of the tuplets which have been found but not yet yielded.
+    current: u64,
+    /// The number represented by the first bit of `current`.
+    current_low: u64,
+}
+
+impl Tuplets {
+    /// Create an iterator over the tuplets with the given pattern whose smallest member lies in
+    /// the interval `[lo, hi)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern does not start at 0 or is not strictly increasing.
+    pub fn new(pattern: &[u64], lo: u64, hi: u64) -> Tuplets {
+        assert!(pattern.first() == Some(&0), "the pattern must start at 0");
+        assert!(pattern.windows(2).all(|w| w[0] < w[1]), "the pattern must be increasing");
+
+        let is_tuplet = |p: u64| pattern.iter().all(|&d| miller_rabin(p + d));
+        let small = SMALL_PRIMES.iter().cloned().filter(|&p| lo <= p && p < hi && is_tuplet(p));
+        let shifts = residue_shifts(pattern);
+        let widest = shifts.iter().flat_map(|entry| entry.1.iter()).cloned().max().unwrap_or(0);
+        let width = pattern.last().cloned().unwrap_or(0);
+
+        Tuplets {
+            small: small.rev().collect(),
+            sieve: SegmentedSieve::new(lo, hi.max(lo).saturating_add(width + 1)),
+            lookahead: (widest / 64) as usize + 1,
+            shifts,
+            words: Vec::new(),
+            idx: 0,
+            words_low: lo - lo % MODULUS,
+            finished: false,
+            high: hi,
+            current: 0,
+            current_low: 0,
+        }
+    }
+
+    /// Searches the next word of the sieve for tuplets, returning the number represented by its
+    /// first bit and the bits of the tuplets it holds, or `None` if the whole interval has been
+    /// searched.
+    fn next_word(&mut self) -> Option<(u64, u64)> {
+        // Sieve more segments until there are enough words to search the next one, throwing away
+        // the words which have already been searched.
+        while !self.finished && self.words.len() - self.idx <= self.lookahead {
+            self.words.drain(..self.idx);
+            self.idx = 0;
+            if self.sieve.advance() {
+                self.words.extend_from_slice(self.sieve.segment());
+            } else {
+                self.finished = true;
+            }
+        }
+        if self.idx == self.words.len() || self.words_low >= self.high {
+            return None;
+        }
+
+        let mut hits = 0;
+        for &(mask, ref distances) in &self.shifts {
+            let mut bits = mask & self.words[self.idx];
+            for &distance in distances {
+                bits &= shifted(&self.words, self.idx, distance);
+            }
+            hits |= bits;
+        }
+
+        // Drop any tuplets starting beyond the end of the interval.
+        if self.high - self.words_low < MODULUS {
+            for (bit, &offset) in OFFSETS.iter().enumerate() {
+                if self.words_low + offset >= self.high {
+                    hits &= !(1 << bit);
+                }
+            }
+        }
+
+        let low = self.words_low;
+        self.idx += 1;
+        self.words_low += MODULUS;
+        Some((low, hits))
+    }
+}
+
+impl Iterator for Tuplets {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if let Some(p) = self.small.pop() {
+            return Some(p);
+        }
+
+        while self.current == 0 {
+            let (low, hits) = self.next_word()?;
+            self.current = hits;
+            self.current_low = low;
+        }
+
+        let bit = self.current.trailing_zeros();
+        self.current &= self.current - 1;
+        Some(self.current_low + OFFSETS[bit as usize])
+    }
+}
+
+/// Returns the number of prime k-tuplets with the given pattern whose smallest member lies in the
+/// interval `[lo, hi)`.
+///
+/// This does the same work as `Tuplets`, but counts the tuplets found in each word of the sieve
+/// all at once.
+///
+/// # Panics
+///
+/// Panics if the pattern does not start at 0 or is not strictly increasing.
+///
+/// # Examples
+///
+/// ```
+/// use primesieve::tuplets::{count_tuplets, COUSINS, OCTUPLETS_3, TRIPLETS_1, TRIPLETS_2};
+///
+/// assert_eq!(count_tuplets(COUSINS, 0, 1000), 41);
+/// assert_eq!(count_tuplets(TRIPLETS_1, 0, 1000000) + count_tuplets(TRIPLETS_2, 0, 1000000),
+///            2837);
+/// assert_eq!(count_tuplets(OCTUPLETS_3, 0, 10000000), 5);
+/// ```
+pub fn count_tuplets(pattern: &[u64], lo: u64, hi: u64) -> u64 {
+    let mut tuplets = Tuplets::new(pattern, lo, hi);
+    let mut count = mem::take(&mut tuplets.small).len() as u64;
+    while let Some((_, hits)) = tuplets.next_word() {
+        count += hits.count_ones() as u64;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sieve::Sieve;
+
+    #[test]
+    fn test_matches_sieve() {
+        let limit = 10000000;
+        let sieve = Sieve::to_limit(limit + 300);
+        let mut is_prime = vec![false; limit as usize + 300];
+        for p in sieve.iter().take_while(|&p| p < limit + 300) {
+            is_prime[p as usize] = true;
+        }
+
+        let patterns: &[&[u64]] = &[
+            TWINS, COUSINS, SEXY, TRIPLETS_1, TRIPLETS_2, QUADRUPLETS, QUINTUPLETS_1, QUINTUPLETS_2,
+            SEXTUPLETS, SEPTUPLETS_1, SEPTUPLETS_2, OCTUPLETS_1, OCTUPLETS_2, OCTUPLETS_3,
+            &[0, 2, 4], &[0, 30, 60], &[0, 210],
+        ];
+        for &pattern in patterns {
+            for &(lo, hi) in &[(0, 1000), (3, 6), (5, 6), (7, 8), (1000, 1000), (12345, 123457),
+                               (0, limit), (7864320, limit - 7)] {
+                let expected = (lo..hi)
+                    .filter(|&p| pattern.iter().all(|&d| is_prime[(p + d) as usize]))
+                    .collect::<Vec<u64>>();
+                assert_eq!(Tuplets::new(pattern, lo, hi).collect::<Vec<u64>>(), expected);
+                assert_eq!(count_tuplets(pattern, lo, hi), expected.len() as u64);
+            }
+        }
+    }
+}
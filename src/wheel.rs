@@ -2,13 +2,17 @@
 //! which are non-multiples of 2, 3 and 5.
 
 const NUM_DIFFS: usize = 8;
+const DIFFS: &[u64; NUM_DIFFS] = &[6, 4, 2, 4, 2, 4, 6, 2];
 
 /// Keeps track of the current state of the wheel.
+///
+/// This is kept as small as possible, since a sieve holds one wheel for every sieving prime.
 pub struct Wheel30 {
     /// The index of the current difference.
     curr_ix: usize,
-    /// The differences that should be cyclically yielded.
-    diffs: [u64; NUM_DIFFS],
+    /// The number whose multiples are being generated - the differences are the multiples of
+    /// this number by the entries of `DIFFS`.
+    num: u64,
 }
 
 impl Wheel30 {
@@ -28,10 +32,9 @@ impl Wheel30 {
             29 => 6,
             _ => unreachable!(),
         };
-        let diffs = [6 * num, 4 * num, 2 * num, 4 * num, 2 * num, 4 * num, 6 * num, 2 * num];
         Wheel30 {
             curr_ix: ix,
-            diffs: diffs,
+            num,
         }
     }
 
@@ -42,7 +45,7 @@ impl Wheel30 {
         if self.curr_ix == NUM_DIFFS {
             self.curr_ix = 0;
         }
-        self.diffs[self.curr_ix]
+        DIFFS[self.curr_ix] * self.num
     }
 }
 